@@ -0,0 +1,142 @@
+// Import necessary libraries
+use std::fmt;
+use icp_contracts::bytesrepr::{self, ToBytes, FromBytes};
+
+// Define the errors returned by contract entry points.
+// Each line gives a variant, its wire code and its message. Codes are what goes
+// over the wire, so they must never be reused or renumbered; new errors get the
+// next free code. This is the only list of codes, so `code()` and `from_code()`
+// cannot disagree.
+macro_rules! time_feed_errors {
+    ($($variant:ident = $code:literal => $message:literal,)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum TimeFeedError {
+            $($variant,)*
+        }
+
+        impl TimeFeedError {
+            // Every error, in code order
+            pub const ALL: &'static [TimeFeedError] = &[$(TimeFeedError::$variant,)*];
+
+            // Get the stable wire code of the error
+            pub fn code(&self) -> u16 {
+                match self {
+                    $(TimeFeedError::$variant => $code,)*
+                }
+            }
+
+            // Get the error for a wire code
+            pub fn from_code(code: u16) -> Option<TimeFeedError> {
+                match code {
+                    $($code => Some(TimeFeedError::$variant),)*
+                    _ => None,
+                }
+            }
+
+            // Get a human readable description of the error
+            pub fn message(&self) -> &'static str {
+                match self {
+                    $(TimeFeedError::$variant => $message,)*
+                }
+            }
+        }
+    };
+}
+
+time_feed_errors! {
+    PostNotFound = 1 => "Post not found",
+    UserNotFound = 2 => "User not found",
+    NotAuthor = 3 => "Post not by user",
+    InsufficientBalance = 4 => "Insufficient balance",
+    AlreadyRegistered = 5 => "Account is already registered",
+    InvalidCredentials = 6 => "Invalid email or password",
+    NotAdmin = 7 => "Caller is not an admin",
+    CategoryNotFound = 8 => "Category not found",
+    CategoryExists = 9 => "Category already exists",
+    CategoryArchived = 10 => "Category is archived",
+    InvalidCategory = 11 => "Invalid category id",
+    InvalidPageSize = 12 => "Page size must be between 1 and 100",
+    InvalidDecayConfig = 13 => "Decay rate needs a non-zero interval",
+    AlreadyVoted = 14 => "Already reacted to this post",
+    NotVoted = 15 => "No reaction to this post",
+    InvalidPassword = 16 => "Password must be between 1 and 1024 bytes",
+    NothingToMigrate = 17 => "There is no legacy state to migrate",
+    EmailTaken = 18 => "Email is already in use",
+    HandleTaken = 19 => "Handle is already in use",
+    InvalidEmail = 20 => "Invalid email address",
+    InvalidHandle = 21 => "Handle must be 3 to 30 letters, digits or underscores",
+    InvalidSession = 22 => "Session not found",
+    SessionExpired = 23 => "Session has expired",
+    InvalidDevice = 24 => "Device label must be at most 64 bytes",
+    InvalidName = 25 => "Name must be 1 to 50 characters",
+    InvalidBio = 26 => "Bio must be at most 280 characters",
+    InvalidLocation = 27 => "Location must be at most 64 characters",
+    InvalidLink = 28 => "Links must be up to 5 http(s) URLs of at most 200 characters",
+    CannotFollowSelf = 29 => "Cannot follow or block yourself",
+    Blocked = 30 => "One of the accounts has blocked the other",
+    AlreadyFollowing = 31 => "Already following this user",
+    NotFollowing = 32 => "Not following this user",
+    NotBlocked = 33 => "Not blocking this user",
+    BalanceOverflow = 34 => "Balance would overflow",
+    MemoTooLong = 35 => "Memo must be at most 32 bytes",
+    NotMinter = 36 => "Caller is not the minter",
+    InsufficientAllowance = 37 => "Insufficient allowance",
+    AllowanceExpired = 38 => "Allowance has expired",
+    SelfApproval = 39 => "Cannot approve yourself",
+    NothingToImport = 40 => "There is no balance to import",
+    AlreadyImported = 41 => "The balance has already been imported",
+    InvalidAmount = 42 => "Invalid amount",
+    StakeBelowMinimum = 43 => "Post would be left below the minimum stake",
+    InvalidSplit = 44 => "Reaction split must add up to 10000 basis points",
+    InvalidFeeSchedule = 45 => "Fees must be at most 1000000 and the initial stake at least the minimum stake",
+    BoostNotShared = 46 => "Author does not share withdrawals with boosters",
+    NotBooster = 47 => "Caller has not boosted this post",
+    BoostSharingLocked = 48 => "Boost sharing cannot be turned off once the post has been boosted",
+}
+
+impl fmt::Display for TimeFeedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (code {})", self.message(), self.code())
+    }
+}
+
+// Errors are encoded as their u16 code
+impl ToBytes for TimeFeedError {
+    fn to_bytes(&self) -> Result<Vec<u8>, bytesrepr::Error> {
+        self.code().to_bytes()
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.code().serialized_length()
+    }
+}
+
+impl FromBytes for TimeFeedError {
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), bytesrepr::Error> {
+        let (code, remainder) = u16::from_bytes(bytes)?;
+        match TimeFeedError::from_code(code) {
+            Some(error) => Ok((error, remainder)),
+            None => Err(bytesrepr::Error::Formatting),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_round_trip() {
+        for error in TimeFeedError::ALL {
+            assert_eq!(TimeFeedError::from_code(error.code()), Some(*error));
+            let bytes = error.to_bytes().unwrap();
+            assert_eq!(bytes, error.code().to_bytes().unwrap());
+            assert_eq!(TimeFeedError::from_bytes(&bytes).unwrap().0, *error);
+        }
+        // Codes are handed out in order from 1, with no gaps
+        let codes: Vec<u16> = TimeFeedError::ALL.iter().map(|error| error.code()).collect();
+        assert_eq!(codes, (1..=TimeFeedError::ALL.len() as u16).collect::<Vec<u16>>());
+        assert_eq!(TimeFeedError::from_code(0), None);
+        assert_eq!(TimeFeedError::from_code(TimeFeedError::ALL.len() as u16 + 1), None);
+    }
+}
@@ -0,0 +1,458 @@
+// Import necessary libraries
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use icp_contracts::account;
+use icp_contracts::balance::TransferError;
+use icp_contracts::bytesrepr::{ToBytes, FromBytes};
+use icp_contracts::hash::{sha256, H256};
+use crate::Error::TimeFeedError;
+
+// Define trait for the host the contracts run against.
+// Everything the contracts need from the chain goes through this trait so that
+// the same entry points can run on a live canister or fully in memory.
+pub trait Host {
+    // Read the raw bytes stored under a state key
+    fn read_state(&self, key: &str) -> Option<Vec<u8>>;
+    // Store raw bytes under a state key
+    fn write_state(&mut self, key: &str, bytes: Vec<u8>);
+    // Delete whatever is stored under a state key
+    fn remove_state(&mut self, key: &str);
+    // Account that called the current entry point
+    fn predecessor_account_id(&self) -> account::Id;
+    // Contract that is currently executing
+    fn predecessor_contract_id(&self) -> account::Id;
+    // Timestamp of the current block, in seconds since the Unix epoch
+    fn block_timestamp(&self) -> u64;
+    // Fresh unpredictable bytes, for salts and tokens
+    fn random_seed(&mut self) -> H256;
+    // Get an account's balance in the chain's balance module, which held $TIME
+    // before the native ledger
+    fn legacy_balance_of(&self, account_id: account::Id) -> u64;
+    // Move $TIME within the chain's balance module
+    fn legacy_transfer(&mut self, from: account::Id, to: account::Id, amount: u64) -> Result<(), TransferError>;
+    // Hand the return value of the current call back to the caller
+    fn ret(&mut self, bytes: Vec<u8>);
+    // Publish an event for off-chain listeners
+    fn emit_event(&mut self, bytes: Vec<u8>);
+    // Abort the current call with an error, discarding its state changes and events
+    fn revert(&mut self, error: TimeFeedError) -> !;
+}
+
+// Define trait for a contract's stored state.
+// Each state type is stored under its own fixed key, so contracts never
+// overwrite each other and stored state stays readable across toolchain
+// upgrades and renames. Keys must never change once deployed.
+pub trait ContractState: ToBytes + FromBytes + Default {
+    const STATE_KEY: &'static str;
+
+    // Build the state of a freshly deployed contract
+    fn init() -> Self {
+        Self::default()
+    }
+}
+
+// Define host that forwards everything to the live chain
+pub struct ChainHost;
+
+impl Host for ChainHost {
+    fn read_state(&self, key: &str) -> Option<Vec<u8>> {
+        icp_contracts::env::storage_read(key.as_bytes())
+    }
+
+    fn write_state(&mut self, key: &str, bytes: Vec<u8>) {
+        icp_contracts::env::storage_write(key.as_bytes(), &bytes);
+    }
+
+    fn remove_state(&mut self, key: &str) {
+        icp_contracts::env::storage_remove(key.as_bytes());
+    }
+
+    fn predecessor_account_id(&self) -> account::Id {
+        icp_contracts::env::predecessor_account_id()
+    }
+
+    fn predecessor_contract_id(&self) -> account::Id {
+        icp_contracts::env::predecessor_contract_id()
+    }
+
+    fn block_timestamp(&self) -> u64 {
+        icp_contracts::env::block_timestamp()
+    }
+
+    fn random_seed(&mut self) -> H256 {
+        icp_contracts::env::random_seed()
+    }
+
+    fn legacy_balance_of(&self, account_id: account::Id) -> u64 {
+        icp_contracts::balance::balance_of(account_id)
+    }
+
+    fn legacy_transfer(&mut self, from: account::Id, to: account::Id, amount: u64) -> Result<(), TransferError> {
+        icp_contracts::balance::transfer(from, to, amount)
+    }
+
+    fn ret(&mut self, bytes: Vec<u8>) {
+        icp_contracts::env::ret(bytes);
+    }
+
+    fn emit_event(&mut self, bytes: Vec<u8>) {
+        icp_contracts::env::emit_event(bytes);
+    }
+
+    // The call is rejected with the error's wire code
+    fn revert(&mut self, error: TimeFeedError) -> ! {
+        icp_contracts::env::revert(error.to_bytes().unwrap())
+    }
+}
+
+// Define enum for why a call on the in-memory host did not succeed
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallError {
+    // The entry point returned an error
+    Failed(TimeFeedError),
+    // The entry point panicked with the given message
+    Panicked(String),
+}
+
+// Define struct for the data held by the in-memory host
+struct InMemoryStore {
+    state: HashMap<String, Vec<u8>>,
+    legacy_balances: HashMap<account::Id, u64>,
+    caller: Option<account::Id>,
+    contract: account::Id,
+    now: u64,
+    seeds: u64,
+    events: Vec<Vec<u8>>,
+    returned: Option<Vec<u8>>,
+    panic: Option<String>,
+}
+
+// Define host that keeps state, events and call results in memory.
+// Clones share the same store, so a test can keep a handle after installing it.
+#[derive(Clone)]
+pub struct InMemoryHost {
+    store: Rc<RefCell<InMemoryStore>>,
+}
+
+impl InMemoryHost {
+    // Create an empty host for the given contract account
+    pub fn new(contract: account::Id) -> Self {
+        InMemoryHost {
+            store: Rc::new(RefCell::new(InMemoryStore {
+                state: HashMap::new(),
+                legacy_balances: HashMap::new(),
+                caller: None,
+                contract: contract,
+                now: 0,
+                seeds: 0,
+                events: Vec::new(),
+                returned: None,
+                panic: None,
+            })),
+        }
+    }
+
+    // Make this host the one used by `env` on the current thread
+    pub fn install(&self) {
+        set_host(Box::new(self.clone()));
+    }
+
+    // Set the account that the next calls are made from
+    pub fn set_caller(&self, caller: account::Id) {
+        self.store.borrow_mut().caller = Some(caller);
+    }
+
+    // Set the block timestamp seen by the next calls
+    pub fn set_block_timestamp(&self, now: u64) {
+        self.store.borrow_mut().now = now;
+    }
+
+    // Move the block timestamp forward by the given number of seconds
+    pub fn advance_time(&self, seconds: u64) {
+        self.store.borrow_mut().now += seconds;
+    }
+
+    // Set an account's balance in the chain's balance module
+    pub fn set_legacy_balance(&self, account_id: account::Id, amount: u64) {
+        self.store.borrow_mut().legacy_balances.insert(account_id, amount);
+    }
+
+    // Get the bytes returned by the last call, if any
+    pub fn returned(&self) -> Option<Vec<u8>> {
+        self.store.borrow().returned.clone()
+    }
+
+    // Get every event emitted so far, oldest first
+    pub fn events(&self) -> Vec<Vec<u8>> {
+        self.store.borrow().events.clone()
+    }
+
+    // Get a copy of everything stored, by state key
+    pub fn stored_state(&self) -> HashMap<String, Vec<u8>> {
+        self.store.borrow().state.clone()
+    }
+
+    // Get the panic message of the last call, if it panicked
+    pub fn last_panic(&self) -> Option<String> {
+        self.store.borrow().panic.clone()
+    }
+
+    // Run an entry point as a single call.
+    // Returns the bytes passed to `env::ret`, or why the call failed. A call that
+    // returns an error or panics has its state, balance module transfers and
+    // events rolled back, the same as a trapped call on chain.
+    pub fn call<F: FnOnce() -> Result<(), TimeFeedError>>(&self, f: F) -> Result<Option<Vec<u8>>, CallError> {
+        let (state, legacy_balances, events) = {
+            let mut store = self.store.borrow_mut();
+            store.returned = None;
+            store.panic = None;
+            (store.state.clone(), store.legacy_balances.clone(), store.events.len())
+        };
+        let error = match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(Ok(())) => return Ok(self.returned()),
+            Ok(Err(error)) => CallError::Failed(error),
+            Err(payload) if payload.is::<TimeFeedError>() => CallError::Failed(*payload.downcast::<TimeFeedError>().unwrap()),
+            Err(payload) => {
+                let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                    message.to_string()
+                } else if let Some(message) = payload.downcast_ref::<String>() {
+                    message.clone()
+                } else {
+                    String::from("unknown panic")
+                };
+                self.store.borrow_mut().panic = Some(message.clone());
+                CallError::Panicked(message)
+            }
+        };
+        let mut store = self.store.borrow_mut();
+        store.state = state;
+        store.legacy_balances = legacy_balances;
+        store.events.truncate(events);
+        store.returned = None;
+        Err(error)
+    }
+}
+
+impl Host for InMemoryHost {
+    fn read_state(&self, key: &str) -> Option<Vec<u8>> {
+        self.store.borrow().state.get(key).cloned()
+    }
+
+    fn write_state(&mut self, key: &str, bytes: Vec<u8>) {
+        self.store.borrow_mut().state.insert(key.to_string(), bytes);
+    }
+
+    fn remove_state(&mut self, key: &str) {
+        self.store.borrow_mut().state.remove(key);
+    }
+
+    fn predecessor_account_id(&self) -> account::Id {
+        self.store.borrow().caller.expect("No caller set on in-memory host")
+    }
+
+    fn predecessor_contract_id(&self) -> account::Id {
+        self.store.borrow().contract
+    }
+
+    fn block_timestamp(&self) -> u64 {
+        self.store.borrow().now
+    }
+
+    // Seeds are derived from a counter so runs are reproducible
+    fn random_seed(&mut self) -> H256 {
+        let mut store = self.store.borrow_mut();
+        store.seeds += 1;
+        sha256(&store.seeds.to_bytes().unwrap())
+    }
+
+    fn legacy_balance_of(&self, account_id: account::Id) -> u64 {
+        *self.store.borrow().legacy_balances.get(&account_id).unwrap_or(&0)
+    }
+
+    fn legacy_transfer(&mut self, from: account::Id, to: account::Id, amount: u64) -> Result<(), TransferError> {
+        let mut store = self.store.borrow_mut();
+        let from_balance = *store.legacy_balances.get(&from).unwrap_or(&0);
+        if from_balance < amount {
+            return Err(TransferError::InsufficientBalance);
+        }
+        if from != to {
+            let to_balance = store.legacy_balances.get(&to).unwrap_or(&0).checked_add(amount).ok_or(TransferError::Overflow)?;
+            store.legacy_balances.insert(from, from_balance - amount);
+            store.legacy_balances.insert(to, to_balance);
+        }
+        Ok(())
+    }
+
+    fn ret(&mut self, bytes: Vec<u8>) {
+        self.store.borrow_mut().returned = Some(bytes);
+    }
+
+    fn emit_event(&mut self, bytes: Vec<u8>) {
+        self.store.borrow_mut().events.push(bytes);
+    }
+
+    // Unwinds to `call`, which rolls the call back and reports the error
+    fn revert(&mut self, error: TimeFeedError) -> ! {
+        panic::panic_any(error)
+    }
+}
+
+thread_local! {
+    static HOST: RefCell<Box<dyn Host>> = RefCell::new(Box::new(ChainHost));
+}
+
+// Replace the host used on the current thread
+pub fn set_host(host: Box<dyn Host>) {
+    HOST.with(|current| *current.borrow_mut() = host);
+}
+
+fn with_host<R>(f: impl FnOnce(&mut dyn Host) -> R) -> R {
+    HOST.with(|current| f(current.borrow_mut().as_mut()))
+}
+
+// Run an entry point as the body of an exported contract method.
+// An error traps the call, which discards its state changes and events, and
+// rejects it with the error's `u16` wire code so callers can branch on it.
+pub fn dispatch<F: FnOnce() -> Result<(), TimeFeedError>>(f: F) {
+    if let Err(error) = f() {
+        with_host(|host| host.revert(error))
+    }
+}
+
+// Drop-in replacement for `icp_contracts::env` backed by the installed host
+pub mod env {
+    use super::*;
+
+    // Load the contract state, or the initial one if nothing has been committed yet
+    pub fn state<T: ContractState>() -> T {
+        match with_host(|host| host.read_state(T::STATE_KEY)) {
+            Some(bytes) => T::from_bytes(&bytes).expect("Corrupt contract state").0,
+            None => T::init(),
+        }
+    }
+
+    // Load state stored under a key by an earlier version of a contract, if any
+    pub fn legacy_state<T: FromBytes>(key: &str) -> Option<T> {
+        let bytes = with_host(|host| host.read_state(key))?;
+        Some(T::from_bytes(&bytes).expect("Corrupt legacy contract state").0)
+    }
+
+    // Store the contract state
+    pub fn commit_state<T: ContractState>(state: T) {
+        let bytes = state.to_bytes().expect("Failed to serialize contract state");
+        with_host(|host| host.write_state(T::STATE_KEY, bytes));
+    }
+
+    // Load a value a contract keeps under its own key, outside its state
+    pub fn read<T: FromBytes>(key: &str) -> Option<T> {
+        let bytes = with_host(|host| host.read_state(key))?;
+        Some(T::from_bytes(&bytes).expect("Corrupt stored value").0)
+    }
+
+    // Store a value under its own key, outside the contract state
+    pub fn write<T: ToBytes>(key: &str, value: &T) {
+        let bytes = value.to_bytes().expect("Failed to serialize stored value");
+        with_host(|host| host.write_state(key, bytes));
+    }
+
+    // Delete a value stored under its own key, such as state left by an earlier version
+    pub fn remove(key: &str) {
+        with_host(|host| host.remove_state(key));
+    }
+
+    pub fn predecessor_account_id() -> account::Id {
+        with_host(|host| host.predecessor_account_id())
+    }
+
+    pub fn predecessor_contract_id() -> account::Id {
+        with_host(|host| host.predecessor_contract_id())
+    }
+
+    pub fn block_timestamp() -> u64 {
+        with_host(|host| host.block_timestamp())
+    }
+
+    pub fn random_seed() -> H256 {
+        with_host(|host| host.random_seed())
+    }
+
+    pub fn legacy_balance_of(account_id: account::Id) -> u64 {
+        with_host(|host| host.legacy_balance_of(account_id))
+    }
+
+    pub fn legacy_transfer(from: account::Id, to: account::Id, amount: u64) -> Result<(), TransferError> {
+        with_host(|host| host.legacy_transfer(from, to, amount))
+    }
+
+    pub fn ret(bytes: Vec<u8>) {
+        with_host(|host| host.ret(bytes));
+    }
+
+    pub fn emit_event(bytes: Vec<u8>) {
+        with_host(|host| host.emit_event(bytes));
+    }
+}
+
+// Helpers for driving the contracts on an in-memory host from tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+
+    // Account the test contract runs as
+    pub(crate) const CONTRACT: u8 = 0xc0;
+
+    // Account with every byte set to `n`, for telling test accounts apart
+    pub(crate) fn account(n: u8) -> account::Id {
+        account::Id::from([n; 32])
+    }
+
+    // Install a fresh in-memory host on the current thread
+    pub(crate) fn new_host() -> InMemoryHost {
+        let host = InMemoryHost::new(account(CONTRACT));
+        host.install();
+        host
+    }
+
+    // Decode the bytes a successful call returned
+    pub(crate) fn decode<T: FromBytes>(returned: Option<Vec<u8>>) -> T {
+        T::from_bytes(&returned.expect("Call returned nothing")).expect("Undecodable return value").0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::testing::{account, new_host, CONTRACT};
+    use crate::PostManagement::like_post;
+    use crate::UserManagement::register_user;
+
+    #[test]
+    fn chain_calls_are_rejected_with_the_error_code() {
+        set_host(Box::new(ChainHost));
+        icp_contracts::env::set_predecessor_contract_id(account(CONTRACT));
+        icp_contracts::env::set_predecessor_account_id(account(1));
+        // The caller gets the code back and nothing the call did is kept
+        let rejected = icp_contracts::env::call(|| dispatch(|| {
+            with_host(|host| host.write_state("time_feed/scratch", vec![1]));
+            like_post(None, H256::zero())
+        }));
+        assert_eq!(rejected, Err(TimeFeedError::PostNotFound.code().to_bytes().unwrap()));
+        assert_eq!(TimeFeedError::from_bytes(&rejected.unwrap_err()).unwrap().0, TimeFeedError::PostNotFound);
+        assert!(icp_contracts::env::storage_read(b"time_feed/scratch").is_none());
+        let register = || register_user(String::from("Alice"), String::from("alice"), String::from("alice@example.com"), b"correct horse".to_vec());
+        assert_eq!(icp_contracts::env::call(|| dispatch(register)), Ok(None));
+        assert!(icp_contracts::env::storage_read(b"time_feed/users").is_some());
+    }
+
+    #[test]
+    fn in_memory_calls_report_dispatched_errors() {
+        let host = new_host();
+        host.set_caller(account(1));
+        assert_eq!(host.call(|| {
+            dispatch(|| like_post(None, H256::zero()));
+            Ok(())
+        }), Err(CallError::Failed(TimeFeedError::PostNotFound)));
+    }
+}
@@ -1,8 +1,19 @@
-use icp_contracts::balance;
-use icp_contracts::balance::{TransferError};
+// Import necessary libraries
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+use icp_contracts::account;
+use icp_contracts::hash::{sha256, H256};
+use icp_contracts::bytesrepr::{ToBytes, FromBytes};
+use crate::Host::{env, ContractState};
+use crate::Error::TimeFeedError;
+use crate::UserManagement::{authenticate, blocked_between, following_of};
+use crate::TokenManagement::Ledger;
 
-// Define struct for post data
-#[derive(Debug, ToBytes, FromBytes)]
+// Define struct for post data.
+// Counters never wrap: `likes` and `dislikes` saturate, and `time`, the post's
+// $TIME stake, never goes below zero. A post whose stake reaches zero expires and
+// is removed straight away, whatever took the last of it.
+#[derive(Debug, Clone, ToBytes, FromBytes)]
 struct Post {
     category: Category,
     heading: String,
@@ -12,24 +23,421 @@ struct Post {
     likes: u64,
     dislikes: u64,
     time: u64,
+    created_at: u64,
+    updated_at: u64,
+    decayed_at: u64,
 }
 
-// Define struct for contract state
+// Define struct for category identifier.
+// Identifiers are lowercase slugs and never change once created, so posts keep
+// pointing at the same category when it is renamed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, ToBytes, FromBytes)]
+pub struct Category(String);
+
+impl Category {
+    // Create a category identifier from a slug, normalizing it to lowercase
+    pub fn new(slug: &str) -> Result<Category, TimeFeedError> {
+        let slug = slug.trim().to_lowercase();
+        if slug.is_empty() || slug.len() > 32 {
+            return Err(TimeFeedError::InvalidCategory);
+        }
+        if !slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+            return Err(TimeFeedError::InvalidCategory);
+        }
+        Ok(Category(slug))
+    }
+}
+
+// Define struct for category data
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+struct CategoryInfo {
+    name: String,
+    archived: bool,
+    created_by: account::Id,
+}
+
+// Define struct for time decay settings.
+// Every `interval` seconds a post loses `rate` $TIME; a rate of 0 turns decay off.
+// The settings are in force from `since` until the next settings replace them.
+#[derive(Debug, Clone, Default, ToBytes, FromBytes)]
+struct DecayConfig {
+    rate: u64,
+    interval: u64,
+    since: u64,
+}
+
+// Define struct for a previous version of an edited post
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+struct PostRevision {
+    category: Category,
+    heading: String,
+    content: String,
+    image: H256,
+    edited_at: u64,
+}
+
+// Define enum for an account's reaction to a post
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ToBytes, FromBytes)]
+enum Reaction {
+    Like,
+    Dislike,
+}
+
+// Define struct for an account's reaction to a post.
+// `time` is how much the reaction moved the post's stake, so taking it back
+// reverses exactly that even if the fee schedule has changed since.
+#[derive(Debug, Clone, Copy, ToBytes, FromBytes)]
+struct Vote {
+    reaction: Reaction,
+    time: u64,
+}
+
+// Define struct for how a reaction fee is shared, in basis points of the fee.
+// The shares always add up to 10000; rounding leftovers go to the author.
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+struct ReactionSplit {
+    author_bps: u16,
+    treasury_bps: u16,
+    burn_bps: u16,
+}
+
+impl Default for ReactionSplit {
+    fn default() -> Self {
+        ReactionSplit {
+            author_bps: 7000,
+            treasury_bps: 2000,
+            burn_bps: 1000,
+        }
+    }
+}
+
+// Define struct for the fees and stakes charged by the contract, in $TIME.
+// Admins can change it with `set_fee_schedule`; every change is kept in `fee_history`.
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+pub struct FeeSchedule {
+    pub create_post: u64,
+    pub like_post: u64,
+    pub dislike_post: u64,
+    pub withdraw: u64,
+    pub initial_stake: u64,
+    pub min_remaining_stake: u64,
+    pub like_stake: u64,
+    pub dislike_penalty: u64,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        FeeSchedule {
+            create_post: 5,
+            like_post: 1,
+            dislike_post: 1,
+            withdraw: 1,
+            initial_stake: 5,
+            min_remaining_stake: 5,
+            like_stake: 1,
+            dislike_penalty: 1,
+        }
+    }
+}
+
+// Define struct for a change to the fee schedule
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+struct FeeChange {
+    fees: FeeSchedule,
+    changed_by: account::Id,
+    changed_at: u64,
+}
+
+// Define struct for who has boosted a post and whether they may withdraw
 #[derive(Debug, ToBytes, FromBytes)]
+struct PostBoosts {
+    shared: bool,
+    boosters: Vec<(account::Id, u64)>,
+}
+
+// Define enum for events emitted by the post contract
+#[derive(Debug, ToBytes, FromBytes)]
+enum PostEvent {
+    Expired { post_id: H256, user_id: account::Id, expired_at: u64 },
+    Deleted { post_id: H256, user_id: account::Id, refunded: u64 },
+}
+
+// Define struct for contract state
+#[derive(Debug, Default, ToBytes, FromBytes)]
 struct State {
-    posts: HashMap<H256, Post>,
+    posts: BTreeMap<H256, Post>,
     nfts: HashMap<H256, H256>,
+    categories: HashMap<Category, CategoryInfo>,
+    admins: HashSet<account::Id>,
+    posts_by_author: HashMap<account::Id, BTreeSet<H256>>,
+    posts_by_category: HashMap<Category, BTreeSet<H256>>,
+    posts_by_author_and_category: HashMap<(account::Id, Category), BTreeSet<H256>>,
+    posts_by_created_at: BTreeSet<(u64, H256)>,
+    next_post_seq: u64,
+    decay_history: Vec<DecayConfig>,
+    sweep_cursor: Option<H256>,
+    reactions: HashMap<H256, HashMap<account::Id, Vote>>,
+    post_history: HashMap<H256, Vec<PostRevision>>,
+    reaction_split: ReactionSplit,
+    earnings: HashMap<account::Id, u64>,
+    fees: FeeSchedule,
+    fee_history: Vec<FeeChange>,
+    boosts: HashMap<H256, BTreeMap<account::Id, u64>>,
+    shared_boosts: HashSet<H256>,
+}
+
+impl State {
+    // Add a post to state and to every index
+    fn insert_post(&mut self, post_id: H256, post: Post) {
+        self.posts_by_author.entry(post.user_id).or_default().insert(post_id);
+        self.posts_by_category.entry(post.category.clone()).or_default().insert(post_id);
+        self.posts_by_author_and_category.entry((post.user_id, post.category.clone())).or_default().insert(post_id);
+        self.posts_by_created_at.insert((post.created_at, post_id));
+        self.posts.insert(post_id, post);
+    }
+
+    // Remove a post from state and from every index
+    fn remove_post(&mut self, post_id: &H256) -> Option<Post> {
+        let post = self.posts.remove(post_id)?;
+        remove_from_index(&mut self.posts_by_author, &post.user_id, post_id);
+        remove_from_index(&mut self.posts_by_category, &post.category, post_id);
+        remove_from_index(&mut self.posts_by_author_and_category, &(post.user_id, post.category.clone()), post_id);
+        self.posts_by_created_at.remove(&(post.created_at, *post_id));
+        self.reactions.remove(post_id);
+        self.post_history.remove(post_id);
+        self.boosts.remove(post_id);
+        self.shared_boosts.remove(post_id);
+        Some(post)
+    }
+
+    // Remove a post whose stake has run out and let listeners know it expired
+    fn expire_post(&mut self, post_id: &H256, now: u64) -> Option<Post> {
+        let post = self.remove_post(post_id)?;
+        let event = PostEvent::Expired { post_id: *post_id, user_id: post.user_id, expired_at: now };
+        env::emit_event(event.to_bytes().unwrap());
+        Some(post)
+    }
+
+    // Bring a post's time up to date with decay. Returns whether the post is
+    // still live; a post that has run out of time is left for the sweep to remove.
+    fn settle_post(&mut self, post_id: &H256, now: u64) -> bool {
+        let post = match self.posts.get_mut(post_id) {
+            Some(post) => post,
+            None => return false,
+        };
+        apply_decay(post, &self.decay_history, now);
+        post.time > 0
+    }
+
+    // Get a copy of a post with decay taken off its time, or none if it has run out
+    fn live_post(&self, post_id: &H256, now: u64) -> Option<Post> {
+        let mut post = self.posts.get(post_id)?.clone();
+        apply_decay(&mut post, &self.decay_history, now);
+        if post.time == 0 {
+            return None;
+        }
+        Some(post)
+    }
+
+    // Get what each booster of a post could take back right now, by account.
+    // Claims are the boosted amounts, scaled down evenly once the post's stake has
+    // fallen below the total boosted. Posts that do not share withdrawals have none.
+    fn boost_claims(&self, post_id: &H256) -> Vec<(account::Id, u64)> {
+        let (post, boosters) = match (self.posts.get(post_id), self.boosts.get(post_id)) {
+            (Some(post), Some(boosters)) if self.shared_boosts.contains(post_id) => (post, boosters),
+            _ => return Vec::new(),
+        };
+        let boosted = saturating_sum(boosters.values().cloned());
+        let backed = post.time.min(boosted);
+        boosters.iter()
+            .map(|(booster, amount)| (*booster, (*amount as u128 * backed as u128 / boosted as u128) as u64))
+            .collect()
+    }
+
+    // Move a post to another category, keeping the category indexes in sync
+    fn move_post(&mut self, post_id: &H256, category: Category) {
+        let post = match self.posts.get_mut(post_id) {
+            Some(post) => post,
+            None => return,
+        };
+        remove_from_index(&mut self.posts_by_category, &post.category, post_id);
+        remove_from_index(&mut self.posts_by_author_and_category, &(post.user_id, post.category.clone()), post_id);
+        self.posts_by_category.entry(category.clone()).or_default().insert(*post_id);
+        self.posts_by_author_and_category.entry((post.user_id, category.clone())).or_default().insert(*post_id);
+        post.category = category;
+    }
+
+    // Get an account's reaction to a post
+    fn reaction_of(&self, post_id: &H256, user_id: &account::Id) -> Option<Reaction> {
+        self.reactions.get(post_id).and_then(|voters| voters.get(user_id)).map(|vote| vote.reaction)
+    }
+
+    // Get live posts by an author
+    fn posts_by_author(&self, user_id: &account::Id, now: u64) -> Vec<(H256, Post)> {
+        self.indexed_posts(self.posts_by_author.get(user_id), now)
+    }
+
+    // Get live posts in a category
+    fn posts_by_category(&self, category: &Category, now: u64) -> Vec<(H256, Post)> {
+        self.indexed_posts(self.posts_by_category.get(category), now)
+    }
+
+    // Get live posts by an author in a category
+    fn posts_by_author_and_category(&self, user_id: &account::Id, category: &Category, now: u64) -> Vec<(H256, Post)> {
+        self.indexed_posts(self.posts_by_author_and_category.get(&(*user_id, category.clone())), now)
+    }
+
+    // Decay only the posts in an index, so queries never have to touch every post
+    fn indexed_posts(&self, post_ids: Option<&BTreeSet<H256>>, now: u64) -> Vec<(H256, Post)> {
+        match post_ids {
+            Some(post_ids) => post_ids.iter().filter_map(|post_id| self.live_post(post_id, now).map(|post| (*post_id, post))).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+// Remove a post id from an index, dropping the entry once it is empty
+fn remove_from_index<K: std::hash::Hash + Eq + Clone>(index: &mut HashMap<K, BTreeSet<H256>>, key: &K, post_id: &H256) {
+    if let Some(post_ids) = index.get_mut(key) {
+        post_ids.remove(post_id);
+        if post_ids.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+impl ContractState for State {
+    const STATE_KEY: &'static str = "time_feed/posts";
+
+    // The contract account is the first admin, so governance is never up for grabs
+    fn init() -> Self {
+        let mut state = State::default();
+        state.admins.insert(env::predecessor_contract_id());
+        state
+    }
+}
+
+// Maximum number of posts returned in a single page
+const MAX_PAGE_SIZE: u32 = 100;
+
+// Highest fee or stake the fee schedule can be set to
+const MAX_FEE: u64 = 1_000_000;
+
+// Define struct for pagination cursor.
+// Clients should treat it as opaque and only pass back what they were given.
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+pub struct PageCursor(H256);
+
+// Define struct for a page of posts, ordered by post id
+#[derive(Debug, ToBytes, FromBytes)]
+struct PostPage {
+    posts: Vec<(H256, Post)>,
+    next_cursor: Option<PageCursor>,
+}
+
+// Build a page of live posts from post ids in ascending order, starting after the cursor
+fn paginate<'a, I: Iterator<Item = &'a H256>>(state: &State, post_ids: I, limit: u32, now: u64) -> Result<PostPage, TimeFeedError> {
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(TimeFeedError::InvalidPageSize);
+    }
+    let mut posts = Vec::new();
+    let mut has_more = false;
+    for post_id in post_ids {
+        if posts.len() == limit as usize {
+            has_more = true;
+            break;
+        }
+        if let Some(post) = state.live_post(post_id, now) {
+            posts.push((*post_id, post));
+        }
+    }
+    // Only hand out a cursor if there is something after this page
+    let next_cursor = if has_more { posts.last().map(|(post_id, _)| PageCursor(*post_id)) } else { None };
+    Ok(PostPage { posts: posts, next_cursor: next_cursor })
+}
+
+// Get the range of post ids that come after a cursor
+fn after_cursor(cursor: &Option<PageCursor>) -> (Bound<H256>, Bound<H256>) {
+    match cursor {
+        Some(PageCursor(post_id)) => (Bound::Excluded(*post_id), Bound::Unbounded),
+        None => (Bound::Unbounded, Bound::Unbounded),
+    }
+}
+
+// Add up counters without wrapping, stopping at the largest value
+fn saturating_sum<I: Iterator<Item = u64>>(values: I) -> u64 {
+    values.fold(0, |total, value| total.saturating_add(value))
+}
+
+// Charge an account for a paid action, moving the $TIME to the contract.
+// All fees go through here so every cost shows up in the ledger. The charge is
+// only staged; it takes effect when the caller commits the ledger.
+fn charge(ledger: &mut Ledger, user_id: account::Id, amount: u64, memo: &[u8]) -> Result<(), TimeFeedError> {
+    ledger.transfer(user_id, env::predecessor_contract_id(), amount, memo)?;
+    Ok(())
+}
+
+// Pay $TIME held by the contract out to an account, staged on the ledger
+fn pay_out(ledger: &mut Ledger, user_id: account::Id, amount: u64, memo: &[u8]) -> Result<(), TimeFeedError> {
+    ledger.transfer(env::predecessor_contract_id(), user_id, amount, memo)?;
+    Ok(())
+}
+
+// Charge a reaction fee, sharing it between the post's author, the treasury and a burn,
+// along with the stake the reaction adds to the post. The stake is held by the
+// contract apart from the split, so every unit of a post's time stays backed
+// whatever the split is. Everything is staged in one ledger call, so it is either
+// fully charged or not charged at all.
+fn charge_reaction(state: &mut State, ledger: &mut Ledger, user_id: account::Id, author: account::Id, amount: u64, stake: u64, memo: &[u8]) -> Result<(), TimeFeedError> {
+    let split = &state.reaction_split;
+    let treasury_share = amount * split.treasury_bps as u64 / 10000;
+    let burn_share = amount * split.burn_bps as u64 / 10000;
+    let author_share = amount - treasury_share - burn_share;
+    let payouts = [(author, author_share), (env::predecessor_contract_id(), treasury_share + stake)];
+    ledger.distribute(user_id, &payouts, burn_share, memo)?;
+    // Track what the author has earned from reactions
+    let earnings = state.earnings.entry(author).or_insert(0);
+    *earnings = earnings.saturating_add(author_share);
+    Ok(())
+}
+
+// Derive the id of a new post from the post counter, its author and its content.
+// The counter is never reused, and ids that would clash with a live post are
+// skipped, so ids are unique and the same sequence of calls always gives the
+// same ids.
+fn next_post_id(state: &mut State, user_id: &account::Id, heading: &str, content: &str, image: &H256) -> H256 {
+    loop {
+        let seq = state.next_post_seq;
+        state.next_post_seq += 1;
+        let mut seed = seq.to_bytes().unwrap();
+        seed.extend(user_id.to_bytes().unwrap());
+        seed.extend(sha256(heading.as_bytes()).to_bytes().unwrap());
+        seed.extend(sha256(content.as_bytes()).to_bytes().unwrap());
+        seed.extend(image.to_bytes().unwrap());
+        let post_id = sha256(&seed);
+        if !state.posts.contains_key(&post_id) {
+            return post_id;
+        }
+    }
 }
 
 // Define function for creating a new post
-fn create_post(category: Category, heading: String, content: String, image: H256) {
-    let state = env::state();
-    let user_id = env::predecessor_account_id();
-    // Check if the user has enough $TIME tokens to create a post
-    let time_cost = 5;
-    if let Err(TransferError::InsufficientBalance) = balance::transfer(user_id, env::predecessor_contract_id(), time_cost) {
-        panic!("Insufficient balance")
+pub fn create_post(session: Option<H256>, category: Category, heading: String, content: String, image: H256) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let mut state: State = env::state();
+    // Check if category exists and is still open for new posts
+    match state.categories.get(&category) {
+        None => return Err(TimeFeedError::CategoryNotFound),
+        Some(info) if info.archived => return Err(TimeFeedError::CategoryArchived),
+        Some(_) => {}
     }
+    // Check if the user has enough $TIME tokens to create a post
+    let time_cost = state.fees.create_post;
+    let mut ledger = Ledger::load();
+    charge(&mut ledger, user_id, time_cost, b"create_post")?;
+    let now = env::block_timestamp();
+    // Generate a unique id for the post
+    let post_id = next_post_id(&mut state, &user_id, &heading, &content, &image);
     // Create new post
     let new_post = Post {
         category: category,
@@ -39,440 +447,1199 @@ fn create_post(category: Category, heading: String, content: String, image: H256
         user_id: user_id,
         likes: 0,
         dislikes: 0,
-        time: 5,
+        time: state.fees.initial_stake,
+        created_at: now,
+        updated_at: now,
+        decayed_at: now,
     };
-    // Generate a unique id for the post
-    let post_id = H256::random();
     // Add new post to state
-    state.posts.insert(post_id, new_post);
-    // Create an NFT for the image
-    let nft_id = H256::random();
-    state.nfts.insert(image, nft_id);
+    state.insert_post(post_id, new_post);
+    // Create an NFT for the image, keeping the existing one if the image was already minted
+    let mut nft_seed = b"nft".to_vec();
+    nft_seed.extend(post_id.to_bytes().unwrap());
+    state.nfts.entry(image).or_insert(sha256(&nft_seed));
     env::commit_state(state);
+    ledger.commit();
     // Return the post id
     env::ret(post_id.to_bytes().unwrap());
+    Ok(())
 }
 
-// Define function for liking a post
-fn like_post(post_id: H256) {
-    let state = env::state();
-    // Check if post exists in state
-   
-    if !state.posts.contains_key(&post_id) {
-        panic!("Post not found");
+// Define function for liking a post.
+// Each account holds at most one reaction per post; liking a post the caller
+// disliked switches the reaction. The liker pays the like fee plus the
+// like stake added to the post's time.
+pub fn like_post(session: Option<H256>, post_id: H256) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let mut state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if !state.settle_post(&post_id, env::block_timestamp()) {
+        return Err(TimeFeedError::PostNotFound);
     }
-    // Check if the user has enough $TIME tokens to like a post
-    let time_cost = 1;
-    if let Err(TransferError::InsufficientBalance) = balance::transfer(env::predecessor_account_id(), env::predecessor_contract_id(), time_cost) {
-        panic!("Insufficient balance")
+    // Check if the user already liked the post
+    let previous = state.reactions.get(&post_id).and_then(|voters| voters.get(&user_id)).cloned();
+    if previous.is_some_and(|vote| vote.reaction == Reaction::Like) {
+        return Err(TimeFeedError::AlreadyVoted);
     }
+    // Check if the user has enough $TIME tokens to like a post
+    let time_cost = state.fees.like_post;
+    let stake = state.fees.like_stake;
+    let author = state.posts[&post_id].user_id;
+    let mut ledger = Ledger::load();
+    charge_reaction(&mut state, &mut ledger, user_id, author, time_cost, stake, b"like_post")?;
     // Get post from state
-    let mut post = state.posts.get_mut(&post_id).unwrap();
+    let post = state.posts.get_mut(&post_id).unwrap();
+    // Take back the previous dislike, if any
+    if let Some(vote) = previous {
+        undo_reaction(post, vote);
+    }
     // Update post data
-    post.likes += 1;
-    post.time += 1;
-    // Commit state
+    post.likes = post.likes.saturating_add(1);
+    post.time = post.time.saturating_add(stake);
+    post.updated_at = env::block_timestamp();
+    // Record the user's reaction
+    state.reactions.entry(post_id).or_default().insert(user_id, Vote { reaction: Reaction::Like, time: stake });
+    // Commit post state and the staged ledger changes together
     env::commit_state(state);
+    ledger.commit();
+    Ok(())
 }
 
-// Define function for disliking a post
-fn dislike_post(post_id: H256) {
-    let state = env::state();
-    // Check if post exists in state
-    if !state.posts.contains_key(&post_id) {
-        panic!("Post not found");
+// Define function for disliking a post.
+// Each account holds at most one reaction per post; disliking a post the caller
+// liked switches the reaction.
+pub fn dislike_post(session: Option<H256>, post_id: H256) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let mut state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if !state.settle_post(&post_id, env::block_timestamp()) {
+        return Err(TimeFeedError::PostNotFound);
+    }
+    // Check if the user already disliked the post
+    let previous = state.reactions.get(&post_id).and_then(|voters| voters.get(&user_id)).cloned();
+    if previous.is_some_and(|vote| vote.reaction == Reaction::Dislike) {
+        return Err(TimeFeedError::AlreadyVoted);
     }
     // Check if the user has enough $TIME tokens to dislike a post
-    let time_cost = 1;
-    if let Err(TransferError::InsufficientBalance) = balance::transfer(env::predecessor_account_id(), env::predecessor_contract_id(), time_cost) {
-        panic!("Insufficient balance")
+    let time_cost = state.fees.dislike_post;
+    let author = state.posts[&post_id].user_id;
+    let mut ledger = Ledger::load();
+    charge_reaction(&mut state, &mut ledger, user_id, author, time_cost, 0, b"dislike_post")?;
+    // Get post from state
+    let post = state.posts.get_mut(&post_id).unwrap();
+    // Take back the previous like, if any
+    if let Some(vote) = previous {
+        undo_reaction(post, vote);
     }
+    // Update post data, taking the penalty off whatever time the post has left
+    let penalty = post.time.min(state.fees.dislike_penalty);
+    post.dislikes = post.dislikes.saturating_add(1);
+    post.time -= penalty;
+    post.updated_at = env::block_timestamp();
+    // Expire the post once its time runs out
+    if post.time == 0 {
+        state.expire_post(&post_id, env::block_timestamp());
+    } else {
+        // Record the user's reaction
+        state.reactions.entry(post_id).or_default().insert(user_id, Vote { reaction: Reaction::Dislike, time: penalty });
+    }
+    // Commit post state and the staged ledger changes together
+    env::commit_state(state);
+    ledger.commit();
+    Ok(())
+}
+
+// Define function for taking back a like or dislike.
+// The fee paid for the reaction is not refunded.
+pub fn unvote(session: Option<H256>, post_id: H256) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let mut state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if !state.settle_post(&post_id, env::block_timestamp()) {
+        return Err(TimeFeedError::PostNotFound);
+    }
+    // Check if the user has reacted to the post
+    let previous = match state.reactions.get_mut(&post_id).and_then(|voters| voters.remove(&user_id)) {
+        Some(reaction) => reaction,
+        None => return Err(TimeFeedError::NotVoted),
+    };
     // Get post from state
-    let mut post = state.posts.get_mut(&post_id).unwrap();
+    let post = state.posts.get_mut(&post_id).unwrap();
     // Update post data
-    post.dislikes += 1;
-    post.time -= 1;
-        // Check if post time is less than or equal to 0
-        if post.time <= 0 {
-            state.posts.remove(&post_id);
+    undo_reaction(post, previous);
+    post.updated_at = env::block_timestamp();
+    // Expire the post once its time runs out
+    if post.time == 0 {
+        state.expire_post(&post_id, env::block_timestamp());
+    }
+    // Commit state
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for getting the caller's reaction to a post
+pub fn get_my_reaction(session: Option<H256>, post_id: H256) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if state.live_post(&post_id, env::block_timestamp()).is_none() {
+        return Err(TimeFeedError::PostNotFound);
+    }
+    // Return the reaction, or none if the caller has not reacted
+    let reaction = state.reaction_of(&post_id, &user_id);
+    env::ret(reaction.to_bytes().unwrap());
+    Ok(())
+}
+
+// Reverse the effect a reaction had on a post's counters and time
+fn undo_reaction(post: &mut Post, vote: Vote) {
+    match vote.reaction {
+        Reaction::Like => {
+            post.likes = post.likes.saturating_sub(1);
+            post.time = post.time.saturating_sub(vote.time);
+        }
+        Reaction::Dislike => {
+            post.dislikes = post.dislikes.saturating_sub(1);
+            post.time = post.time.saturating_add(vote.time);
         }
-        // Commit state
-        env::commit_state(state);
     }
+}
 
-// Define function for getting a post
-fn get_post(post_id: H256) {
-    let state = env::state();
-    // Check if post exists in state
-    if !state.posts.contains_key(&post_id) {
-        panic!("Post not found");
+// Define function for editing a post.
+// Only the author can edit a post. The version being replaced is kept in the
+// post's edit history.
+pub fn edit_post(session: Option<H256>, post_id: H256, category: Category, heading: String, content: String, image: H256) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let mut state: State = env::state();
+    let now = env::block_timestamp();
+    // Check if post exists in state, once decay has been taken off its time
+    if !state.settle_post(&post_id, now) {
+        return Err(TimeFeedError::PostNotFound);
     }
-    // Get post from state
+    // Check if post is by the caller
+    let post = state.posts.get(&post_id).unwrap();
+    if post.user_id != user_id {
+        return Err(TimeFeedError::NotAuthor);
+    }
+    // Check if the new category exists and is still open for posts
+    if category != post.category {
+        match state.categories.get(&category) {
+            None => return Err(TimeFeedError::CategoryNotFound),
+            Some(info) if info.archived => return Err(TimeFeedError::CategoryArchived),
+            Some(_) => {}
+        }
+    }
+    // Keep the current version in the edit history
+    let revision = PostRevision {
+        category: post.category.clone(),
+        heading: post.heading.clone(),
+        content: post.content.clone(),
+        image: post.image,
+        edited_at: now,
+    };
+    state.post_history.entry(post_id).or_default().push(revision);
+    // Update post data
+    state.move_post(&post_id, category);
+    let post = state.posts.get_mut(&post_id).unwrap();
+    post.heading = heading;
+    post.content = content;
+    post.image = image;
+    post.updated_at = now;
+    // Create an NFT for the new image, keeping the existing one if the image was already minted
+    let mut nft_seed = b"nft".to_vec();
+    nft_seed.extend(post_id.to_bytes().unwrap());
+    nft_seed.extend(image.to_bytes().unwrap());
+    state.nfts.entry(image).or_insert(sha256(&nft_seed));
+    // Commit state
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for deleting a post.
+// Only the author can delete a post. Whatever time is left on the post is paid
+// back to the author, and its reactions and edit history are dropped with it.
+pub fn delete_post(session: Option<H256>, post_id: H256) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let mut state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if !state.settle_post(&post_id, env::block_timestamp()) {
+        return Err(TimeFeedError::PostNotFound);
+    }
+    // Check if post is by the caller
     let post = state.posts.get(&post_id).unwrap();
+    if post.user_id != user_id {
+        return Err(TimeFeedError::NotAuthor);
+    }
+    // Pay boosters back their share, if the author shares withdrawals
+    let mut ledger = Ledger::load();
+    let mut refund = post.time;
+    for (booster, claim) in state.boost_claims(&post_id) {
+        if claim > 0 {
+            pay_out(&mut ledger, booster, claim, b"delete_post")?;
+            refund -= claim;
+        }
+    }
+    // Pay the remaining time back to the author
+    if refund > 0 {
+        pay_out(&mut ledger, user_id, refund, b"delete_post")?;
+    }
+    // Remove post from state
+    state.remove_post(&post_id);
+    env::emit_event(PostEvent::Deleted { post_id: post_id, user_id: user_id, refunded: refund }.to_bytes().unwrap());
+    // Commit post state and the staged ledger changes together
+    env::commit_state(state);
+    ledger.commit();
+    Ok(())
+}
+
+// Define function for getting the edit history of a post, oldest first
+pub fn get_post_history(post_id: H256) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if state.live_post(&post_id, env::block_timestamp()).is_none() {
+        return Err(TimeFeedError::PostNotFound);
+    }
+    // Return the previous versions of the post
+    let history = state.post_history.get(&post_id).cloned().unwrap_or_default();
+    env::ret(history.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting a post
+pub fn get_post(post_id: H256) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Get post from state, once decay has been taken off its time
+    let post = match state.live_post(&post_id, env::block_timestamp()) {
+        Some(post) => post,
+        None => return Err(TimeFeedError::PostNotFound),
+    };
     // Return post data
     env::ret(post.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define function for getting all posts
-fn get_all_posts() {
-    let state = env::state();
-    // Return all posts
-    env::ret(state.posts.to_bytes().unwrap());
+pub fn get_all_posts() -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    let now = env::block_timestamp();
+    // Return all posts that are still live
+    let posts: BTreeMap<H256, Post> = state.posts.keys().filter_map(|post_id| state.live_post(post_id, now).map(|post| (*post_id, post))).collect();
+    env::ret(posts.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define function for getting all posts in a category
-fn get_posts_by_category(category: Category) {
-    let state = env::state();
-    // Create a new hashmap for posts in the category
+pub fn get_posts_by_category(category: Category) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if category exists in registry
+    if !state.categories.contains_key(&category) {
+        return Err(TimeFeedError::CategoryNotFound);
+    }
+    // Collect posts in the category from the index
     let mut posts_in_category = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is in the category
-        if post.category == category {
-            // Add post to hashmap
-            posts_in_category.insert(*post_id, *post);
-        }
+    for (post_id, post) in state.posts_by_category(&category, env::block_timestamp()) {
+        posts_in_category.insert(post_id, post);
     }
     // Return posts in category
     env::ret(posts_in_category.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define function for getting all posts by a user
-fn get_posts_by_user(user_id: account::Id) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user
+pub fn get_posts_by_user(user_id: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Collect posts by the user from the index
     let mut posts_by_user = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user
-        if post.user_id == user_id {
-            // Add post to hashmap
-            posts_by_user.insert(*post_id, *post);
-        }
+    for (post_id, post) in state.posts_by_author(&user_id, env::block_timestamp()) {
+        posts_by_user.insert(post_id, post);
     }
     // Return posts by user
     env::ret(posts_by_user.to_bytes().unwrap());
+    Ok(())
 }
 
 
 // Define function for getting all posts by a user in a category
-fn get_posts_by_user_and_category(user_id: account::Id, category: Category) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user in the category
+pub fn get_posts_by_user_and_category(user_id: account::Id, category: Category) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if category exists in registry
+    if !state.categories.contains_key(&category) {
+        return Err(TimeFeedError::CategoryNotFound);
+    }
+    // Collect posts by the user in the category from the index
     let mut posts_by_user_and_category = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user and in the category
-        if post.user_id == user_id && post.category == category {
-            // Add post to hashmap
-            posts_by_user_and_category.insert(*post_id, *post);
-        }
+    for (post_id, post) in state.posts_by_author_and_category(&user_id, &category, env::block_timestamp()) {
+        posts_by_user_and_category.insert(post_id, post);
     }
     // Return posts by user in category
     env::ret(posts_by_user_and_category.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting a page of all posts
+pub fn get_all_posts_page(limit: u32, cursor: Option<PageCursor>) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    let post_ids = state.posts.range(after_cursor(&cursor)).map(|(post_id, _)| post_id);
+    let page = paginate(&state, post_ids, limit, env::block_timestamp())?;
+    // Return page of posts
+    env::ret(page.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define struct for cursor of a page ordered by creation time
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+pub struct CreationCursor(u64, H256);
+
+// Define struct for a page of posts ordered by creation time
+#[derive(Debug, ToBytes, FromBytes)]
+struct CreationPage {
+    posts: Vec<(H256, Post)>,
+    next_cursor: Option<CreationCursor>,
+}
+
+// Check if a range has no keys in it, which `BTreeSet::range` would panic on
+fn range_is_empty<K: Ord>(lower: &Bound<K>, upper: &Bound<K>) -> bool {
+    match (lower, upper) {
+        (Bound::Included(l), Bound::Included(u)) => l > u,
+        (Bound::Included(l), Bound::Excluded(u)) | (Bound::Excluded(l), Bound::Included(u)) => l >= u,
+        (Bound::Excluded(l), Bound::Excluded(u)) => l >= u,
+        _ => false,
+    }
+}
+
+// Define function for getting a page of posts ordered by creation time.
+// Only posts created within [from, to] are returned, optionally limited to an
+// author and/or category. Posts created in the same second are ordered by id.
+pub fn get_posts_by_creation_time(
+    from: Option<u64>,
+    to: Option<u64>,
+    user_id: Option<account::Id>,
+    category: Option<Category>,
+    newest_first: bool,
+    limit: u32,
+    cursor: Option<CreationCursor>,
+) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(TimeFeedError::InvalidPageSize);
+    }
+    // Check if category exists in registry
+    if let Some(category) = &category {
+        if !state.categories.contains_key(category) {
+            return Err(TimeFeedError::CategoryNotFound);
+        }
+    }
+    // Narrow the time index to the requested window, continuing after the cursor
+    let mut lower = Bound::Included((from.unwrap_or(0), H256::zero()));
+    let mut upper = Bound::Included((to.unwrap_or(u64::MAX), H256::repeat_byte(0xff)));
+    if let Some(CreationCursor(created_at, post_id)) = cursor {
+        if newest_first {
+            upper = Bound::Excluded((created_at, post_id));
+        } else {
+            lower = Bound::Excluded((created_at, post_id));
+        }
+    }
+    let entries: Box<dyn Iterator<Item = &(u64, H256)>> = if range_is_empty(&lower, &upper) {
+        Box::new(std::iter::empty())
+    } else if newest_first {
+        Box::new(state.posts_by_created_at.range((lower, upper)).rev())
+    } else {
+        Box::new(state.posts_by_created_at.range((lower, upper)))
+    };
+    // Collect matching live posts until the page is full
+    let now = env::block_timestamp();
+    let mut posts = Vec::new();
+    let mut has_more = false;
+    for (_, post_id) in entries {
+        let post = match state.live_post(post_id, now) {
+            Some(post) => post,
+            None => continue,
+        };
+        if user_id.is_some_and(|user_id| post.user_id != user_id) {
+            continue;
+        }
+        if category.as_ref().is_some_and(|category| &post.category != category) {
+            continue;
+        }
+        if posts.len() == limit as usize {
+            has_more = true;
+            break;
+        }
+        posts.push((*post_id, post));
+    }
+    let next_cursor = if has_more { posts.last().map(|(post_id, post)| CreationCursor(post.created_at, *post_id)) } else { None };
+    // Return page of posts
+    env::ret(CreationPage { posts: posts, next_cursor: next_cursor }.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting a page of posts in a category
+pub fn get_posts_by_category_page(category: Category, limit: u32, cursor: Option<PageCursor>) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if category exists in registry
+    if !state.categories.contains_key(&category) {
+        return Err(TimeFeedError::CategoryNotFound);
+    }
+    let page = match state.posts_by_category.get(&category) {
+        Some(post_ids) => paginate(&state, post_ids.range(after_cursor(&cursor)), limit, env::block_timestamp())?,
+        None => paginate(&state, std::iter::empty(), limit, env::block_timestamp())?,
+    };
+    // Return page of posts in category
+    env::ret(page.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting a page of posts by a user
+pub fn get_posts_by_user_page(user_id: account::Id, limit: u32, cursor: Option<PageCursor>) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    let page = match state.posts_by_author.get(&user_id) {
+        Some(post_ids) => paginate(&state, post_ids.range(after_cursor(&cursor)), limit, env::block_timestamp())?,
+        None => paginate(&state, std::iter::empty(), limit, env::block_timestamp())?,
+    };
+    // Return page of posts by user
+    env::ret(page.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting a page of posts by a user in a category
+pub fn get_posts_by_user_and_category_page(user_id: account::Id, category: Category, limit: u32, cursor: Option<PageCursor>) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if category exists in registry
+    if !state.categories.contains_key(&category) {
+        return Err(TimeFeedError::CategoryNotFound);
+    }
+    let page = match state.posts_by_author_and_category.get(&(user_id, category)) {
+        Some(post_ids) => paginate(&state, post_ids.range(after_cursor(&cursor)), limit, env::block_timestamp())?,
+        None => paginate(&state, std::iter::empty(), limit, env::block_timestamp())?,
+    };
+    // Return page of posts by user in category
+    env::ret(page.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define struct for cursor of a home feed page
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+pub struct FeedCursor(u64, H256);
+
+// Define struct for a page of the home feed, best ranked first
+#[derive(Debug, ToBytes, FromBytes)]
+struct FeedPage {
+    posts: Vec<(H256, Post)>,
+    next_cursor: Option<FeedCursor>,
+}
+
+// Rank a post for the home feed.
+// The score grows with the post's time stake and its share of likes, and falls
+// off with the hours since it was created.
+fn feed_score(post: &Post, now: u64) -> u64 {
+    let stake = post.time as u128 + 1;
+    let approval = (post.likes as u128 + 1) * 1000 / (post.likes as u128 + post.dislikes as u128 + 2);
+    let age_hours = now.saturating_sub(post.created_at) as u128 / 3600;
+    let score = stake * approval / (age_hours + 2);
+    score.min(u64::MAX as u128) as u64
+}
+
+// Define function for getting the caller's home feed.
+// Merges posts by the accounts the caller follows with posts in the given
+// categories, skipping blocked authors, ranked by `feed_score`. Scores move as
+// posts age, so a cursor only guarantees no repeats within a short session.
+pub fn get_home_feed(session: Option<H256>, categories: Vec<Category>, limit: u32, cursor: Option<FeedCursor>) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let state: State = env::state();
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(TimeFeedError::InvalidPageSize);
+    }
+    // Check if categories exist in registry
+    for category in categories.iter() {
+        if !state.categories.contains_key(category) {
+            return Err(TimeFeedError::CategoryNotFound);
+        }
+    }
+    // Collect candidate posts from the author and category indexes
+    let blocked = blocked_between(&user_id);
+    let mut candidates = BTreeSet::new();
+    for author in following_of(&user_id) {
+        if let Some(post_ids) = state.posts_by_author.get(&author) {
+            candidates.extend(post_ids.iter().cloned());
+        }
+    }
+    for category in categories.iter() {
+        if let Some(post_ids) = state.posts_by_category.get(category) {
+            candidates.extend(post_ids.iter().cloned());
+        }
+    }
+    // Rank candidates, best score first and post id breaking ties
+    let now = env::block_timestamp();
+    let mut ranked: Vec<(u64, H256, Post)> = candidates.into_iter()
+        .filter_map(|post_id| state.live_post(&post_id, now).map(|post| (post_id, post)))
+        .filter(|(_, post)| !blocked.contains(&post.user_id))
+        .map(|(post_id, post)| (feed_score(&post, now), post_id, post))
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    // Skip everything up to and including the cursor
+    let start = match &cursor {
+        Some(FeedCursor(score, post_id)) => ranked.iter()
+            .position(|(s, id, _)| s < score || (s == score && id > post_id))
+            .unwrap_or(ranked.len()),
+        None => 0,
+    };
+    let ranked_count = ranked.len();
+    let page: Vec<(u64, H256, Post)> = ranked.drain(start..).take(limit as usize).collect();
+    let next_cursor = if start + page.len() < ranked_count {
+        page.last().map(|(score, post_id, _)| FeedCursor(*score, *post_id))
+    } else {
+        None
+    };
+    let posts = page.into_iter().map(|(_, post_id, post)| (post_id, post)).collect();
+    // Return page of the home feed
+    env::ret(FeedPage { posts: posts, next_cursor: next_cursor }.to_bytes().unwrap());
+    Ok(())
 }
 
 //  Define function for getting highest post time of an user
-fn get_highest_post_time(user_id: account::Id) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user
-    let mut posts_by_user = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user
-        if post.user_id == user_id {
-            // Add post to hashmap
-            posts_by_user.insert(*post_id, *post);
-        }
-    }
-    // Create a new vector for post times
-    let mut post_times = Vec::new();
-    // Iterate through all posts in the hashmap
-    for post in posts_by_user.values() {
-        // Add post time to vector
-        post_times.push(post.time);
-    }
-    // Sort vector in descending order
-    post_times.sort_by(|a, b| b.cmp(a));
+pub fn get_highest_post_time(user_id: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Find highest post time among posts by the user, 0 if they have none
+    let highest = state.posts_by_author(&user_id, env::block_timestamp()).iter().map(|(_, post)| post.time).max().unwrap_or(0);
     // Return highest post time
-    env::ret(post_times[0].to_bytes().unwrap());
+    env::ret(highest.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define function for getting highest post time of an user in a category
-fn get_highest_post_time_by_category(user_id: account::Id, category: Category) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user in the category
-    let mut posts_by_user_and_category = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user and in the category
-        if post.user_id == user_id && post.category == category {
-            // Add post to hashmap
-            posts_by_user_and_category.insert(*post_id, *post);
-        }
-    }
-    // Create a new vector for post times
-    let mut post_times = Vec::new();
-    // Iterate through all posts in the hashmap
-    for post in posts_by_user_and_category.values() {
-        // Add post time to vector
-        post_times.push(post.time);
-    }
-    // Sort vector in descending order
-    post_times.sort_by(|a, b| b.cmp(a));
+pub fn get_highest_post_time_by_category(user_id: account::Id, category: Category) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if category exists in registry
+    if !state.categories.contains_key(&category) {
+        return Err(TimeFeedError::CategoryNotFound);
+    }
+    // Find highest post time among posts by the user in the category, 0 if they have none
+    let highest = state.posts_by_author_and_category(&user_id, &category, env::block_timestamp()).iter().map(|(_, post)| post.time).max().unwrap_or(0);
     // Return highest post time
-    env::ret(post_times[0].to_bytes().unwrap());
+    env::ret(highest.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define a function for getting the number of posts by a user
-fn get_number_of_posts_by_user(user_id: account::Id) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user
-    let mut posts_by_user = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user
-        if post.user_id == user_id {
-            // Add post to hashmap
-            posts_by_user.insert(*post_id, *post);
-        }
-    }
+pub fn get_number_of_posts_by_user(user_id: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
     // Return number of posts by user
-    env::ret(posts_by_user.len().to_bytes().unwrap());
+    env::ret((state.posts_by_author(&user_id, env::block_timestamp()).len() as u64).to_bytes().unwrap());
+    Ok(())
 }
 
 // Define a function for getting the number of posts by a user in a category
-fn get_number_of_posts_by_user_and_category(user_id: account::Id, category: Category) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user in the category
-    let mut posts_by_user_and_category = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user and in the category
-        if post.user_id == user_id && post.category == category {
-            // Add post to hashmap
-            posts_by_user_and_category.insert(*post_id, *post);
-        }
+pub fn get_number_of_posts_by_user_and_category(user_id: account::Id, category: Category) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if category exists in registry
+    if !state.categories.contains_key(&category) {
+        return Err(TimeFeedError::CategoryNotFound);
     }
     // Return number of posts by user in category
-    env::ret(posts_by_user_and_category.len().to_bytes().unwrap());
+    env::ret((state.posts_by_author_and_category(&user_id, &category, env::block_timestamp()).len() as u64).to_bytes().unwrap());
+    Ok(())
 }
 
 // Define a function for getting total likes for all the post for a user
-fn get_total_likes_by_user(user_id: account::Id) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user
-    let mut posts_by_user = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user
-        if post.user_id == user_id {
-            // Add post to hashmap
-            posts_by_user.insert(*post_id, *post);
-        }
-    }
-    // Create a new vector for post likes
-    let mut post_likes = Vec::new();
-    // Iterate through all posts in the hashmap
-    for post in posts_by_user.values() {
-        // Add post likes to vector
-        post_likes.push(post.likes);
-    }
+pub fn get_total_likes_by_user(user_id: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
     // Return total likes for all posts by user
-    env::ret(post_likes.iter().sum::<u64>().to_bytes().unwrap());
+    let total = saturating_sum(state.posts_by_author(&user_id, env::block_timestamp()).iter().map(|(_, post)| post.likes));
+    env::ret(total.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define a function for getting total dislikes for all the post for a user 
-fn get_total_dislikes_by_user(user_id: account::Id) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user
-    let mut posts_by_user = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user
-        if post.user_id == user_id {
-            // Add post to hashmap
-            posts_by_user.insert(*post_id, *post);
-        }
-    }
-    // Create a new vector for post dislikes
-    let mut post_dislikes = Vec::new();
-    // Iterate through all posts in the hashmap
-    for post in posts_by_user.values() {
-        // Add post dislikes to vector
-        post_dislikes.push(post.dislikes);
-    }
+pub fn get_total_dislikes_by_user(user_id: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
     // Return total dislikes for all posts by user
-    env::ret(post_dislikes.iter().sum::<u64>().to_bytes().unwrap());
+    let total = saturating_sum(state.posts_by_author(&user_id, env::block_timestamp()).iter().map(|(_, post)| post.dislikes));
+    env::ret(total.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define a function for getting total time for all the post for a user
-fn get_total_time_by_user(user_id: account::Id) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user
-    let mut posts_by_user = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user
-        if post.user_id == user_id {
-            // Add post to hashmap
-            posts_by_user.insert(*post_id, *post);
-        }
-    }
-    // Create a new vector for post time
-    let mut post_time = Vec::new();
-    // Iterate through all posts in the hashmap
-    for post in posts_by_user.values() {
-        // Add post time to vector
-        post_time.push(post.time);
-    }
+pub fn get_total_time_by_user(user_id: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
     // Return total time for all posts by user
-    env::ret(post_time.iter().sum::<u64>().to_bytes().unwrap());
+    let total = saturating_sum(state.posts_by_author(&user_id, env::block_timestamp()).iter().map(|(_, post)| post.time));
+    env::ret(total.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define a function for getting total likes for all the post for a user in a category
-fn get_total_likes_by_user_and_category(user_id: account::Id, category: String) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user in the category
-    let mut posts_by_user_and_category = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user and in the category
-        if post.user_id == user_id && post.category == category {
-            // Add post to hashmap
-            posts_by_user_and_category.insert(*post_id, *post);
-        }
-    }
-    // Create a new vector for post likes
-    let mut post_likes = Vec::new();
-    // Iterate through all posts in the hashmap
-    for post in posts_by_user_and_category.values() {
-        // Add post likes to vector
-        post_likes.push(post.likes);
+pub fn get_total_likes_by_user_and_category(user_id: account::Id, category: Category) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if category exists in registry
+    if !state.categories.contains_key(&category) {
+        return Err(TimeFeedError::CategoryNotFound);
     }
     // Return total likes for all posts by user in category
-    env::ret(post_likes.iter().sum::<u64>().to_bytes().unwrap());
+    let total = saturating_sum(state.posts_by_author_and_category(&user_id, &category, env::block_timestamp()).iter().map(|(_, post)| post.likes));
+    env::ret(total.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define a function for getting total dislikes for all the post for a user in a category
-fn get_total_dislikes_by_user_and_category(user_id: account::Id, category: String) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user in the category
-    let mut posts_by_user_and_category = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user and in the category
-        if post.user_id == user_id && post.category == category {
-            // Add post to hashmap
-            posts_by_user_and_category.insert(*post_id, *post);
-        }
-    }
-    // Create a new vector for post dislikes
-    let mut post_dislikes = Vec::new();
-    // Iterate through all posts in the hashmap
-    for post in posts_by_user_and_category.values() {
-        // Add post dislikes to vector
-        post_dislikes.push(post.dislikes);
+pub fn get_total_dislikes_by_user_and_category(user_id: account::Id, category: Category) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if category exists in registry
+    if !state.categories.contains_key(&category) {
+        return Err(TimeFeedError::CategoryNotFound);
     }
     // Return total dislikes for all posts by user in category
-    env::ret(post_dislikes.iter().sum::<u64>().to_bytes().unwrap());
+    let total = saturating_sum(state.posts_by_author_and_category(&user_id, &category, env::block_timestamp()).iter().map(|(_, post)| post.dislikes));
+    env::ret(total.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define a function for getting total time for all the post for a user in a category
-fn get_total_time_by_user_and_category(user_id: account::Id, category: String) {
-    let state = env::state();
-    // Create a new hashmap for posts by the user in the category
-    let mut posts_by_user_and_category = HashMap::new();
-    // Iterate through all posts in state
-    for (post_id, post) in state.posts.iter() {
-        // Check if post is by the user and in the category
-        if post.user_id == user_id && post.category == category {
-            // Add post to hashmap
-            posts_by_user_and_category.insert(*post_id, *post);
-        }
-    }
-    // Create a new vector for post time
-    let mut post_time = Vec::new();
-    // Iterate through all posts in the hashmap
-    for post in posts_by_user_and_category.values() {
-        // Add post time to vector
-        post_time.push(post.time);
+pub fn get_total_time_by_user_and_category(user_id: account::Id, category: Category) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if category exists in registry
+    if !state.categories.contains_key(&category) {
+        return Err(TimeFeedError::CategoryNotFound);
     }
     // Return total time for all posts by user in category
-    env::ret(post_time.iter().sum::<u64>().to_bytes().unwrap());
+    let total = saturating_sum(state.posts_by_author_and_category(&user_id, &category, env::block_timestamp()).iter().map(|(_, post)| post.time));
+    env::ret(total.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define a function for getting total likes for a specific post by user
-fn get_total_likes_by_user_and_post(user_id: account::Id, post_id: H256) {
-    let state = env::state();
-    // Check if post exists in state
-    if !state.posts.contains_key(&post_id) {
-        panic!("Post not found");
-    }
+pub fn get_total_likes_by_user_and_post(user_id: account::Id, post_id: H256) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Get post from state, once decay has been taken off its time
+    let post = match state.live_post(&post_id, env::block_timestamp()) {
+        Some(post) => post,
+        None => return Err(TimeFeedError::PostNotFound),
+    };
     // Check if post is by the user
-    let post = state.posts.get(&post_id).unwrap();
     if post.user_id != user_id {
-        panic!("Post not by user");
+        return Err(TimeFeedError::NotAuthor);
     }
     // Return total likes for post by user
     env::ret(post.likes.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define a function for getting total dislikes for a specific post by user
-fn get_total_dislikes_by_user_and_post(user_id: account::Id, post_id: H256) {
-    let state = env::state();
-    // Check if post exists in state
-    if !state.posts.contains_key(&post_id) {
-        panic!("Post not found");
-    }
+pub fn get_total_dislikes_by_user_and_post(user_id: account::Id, post_id: H256) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Get post from state, once decay has been taken off its time
+    let post = match state.live_post(&post_id, env::block_timestamp()) {
+        Some(post) => post,
+        None => return Err(TimeFeedError::PostNotFound),
+    };
     // Check if post is by the user
-    let post = state.posts.get(&post_id).unwrap();
     if post.user_id != user_id {
-        panic!("Post not by user");
+        return Err(TimeFeedError::NotAuthor);
     }
     // Return total dislikes for post by user
     env::ret(post.dislikes.to_bytes().unwrap());
+    Ok(())
 }
 
 
 // Define a function for getting total time for a specific post by user
-fn get_total_time_by_user_and_post(user_id: account::Id, post_id: H256) {
-    let state = env::state();
-    // Check if post exists in state
-    if !state.posts.contains_key(&post_id) {
-        panic!("Post not found");
-    }
+pub fn get_total_time_by_user_and_post(user_id: account::Id, post_id: H256) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Get post from state, once decay has been taken off its time
+    let post = match state.live_post(&post_id, env::block_timestamp()) {
+        Some(post) => post,
+        None => return Err(TimeFeedError::PostNotFound),
+    };
     // Check if post is by the user
-    let post = state.posts.get(&post_id).unwrap();
     if post.user_id != user_id {
-        panic!("Post not by user");
+        return Err(TimeFeedError::NotAuthor);
     }
     // Return total time for post by user
     env::ret(post.time.to_bytes().unwrap());
+    Ok(())
 }
 
-// Define a function for getting total time and being able to withdraw it for a specific post by a user which will decrease post time depending on how much time is withdrawn
-fn withdraw_time_by_user_and_post(user_id: account::Id, post_id: H256) {
-    let state = env::state();
-    // Check if post exists in state
-    if !state.posts.contains_key(&post_id) {
-        panic!("Post not found");
+// Define a function for withdrawing time from a specific post by its author.
+// Moves `amount` of the post's stake to the author's balance, minus the
+// withdrawal fee. A post must keep at least the minimum remaining stake after a partial
+// withdrawal; withdrawing the whole stake removes the post. Returns the new stake.
+pub fn withdraw_time_by_user_and_post(session: Option<H256>, post_id: H256, amount: u64) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let mut state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if !state.settle_post(&post_id, env::block_timestamp()) {
+        return Err(TimeFeedError::PostNotFound);
     }
     // Check if post is by the user
     let post = state.posts.get(&post_id).unwrap();
     if post.user_id != user_id {
-        panic!("Post not by user");
+        return Err(TimeFeedError::NotAuthor);
+    }
+    // Check if the amount can be withdrawn
+    // Time boosters can claim back is not the author's to withdraw
+    let time_cost = state.fees.withdraw;
+    let reserved = saturating_sum(state.boost_claims(&post_id).into_iter().map(|(_, claim)| claim));
+    if amount <= time_cost || amount > post.time - reserved {
+        return Err(TimeFeedError::InvalidAmount);
     }
-    // Check if the user has enough $TIME tokens to withdraw
-    let time_cost = 1;
-    if let Err(TransferError::InsufficientBalance) = balance::transfer(user_id, env::predecessor_contract_id(), time_cost) {
-        panic!("Insufficient balance");
+    let remaining = post.time - amount;
+    if remaining > 0 && remaining < state.fees.min_remaining_stake {
+        return Err(TimeFeedError::StakeBelowMinimum);
     }
+    // Pay the withdrawn time, minus the fee, out to the author
+    let mut ledger = Ledger::load();
+    pay_out(&mut ledger, user_id, amount - time_cost, b"withdraw_time")?;
     // Update post data
-    let mut post = state.posts.get_mut(&post_id).unwrap();
-    post.time -= 1;
-    // Commit state
+    let post = state.posts.get_mut(&post_id).unwrap();
+    post.time = remaining;
+    post.updated_at = env::block_timestamp();
+    // Expire the post once all of its time has been withdrawn
+    if post.time == 0 {
+        state.expire_post(&post_id, env::block_timestamp());
+    }
+    // Commit post state and the staged ledger changes together
+    env::commit_state(state);
+    ledger.commit();
+    // Return the new stake
+    env::ret(remaining.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for staking $TIME into any post.
+// The author or any supporter can add an arbitrary amount to a post's stake;
+// each booster's total is recorded. Returns the new stake.
+pub fn boost_post(session: Option<H256>, post_id: H256, amount: u64) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let mut state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if !state.settle_post(&post_id, env::block_timestamp()) {
+        return Err(TimeFeedError::PostNotFound);
+    }
+    // Check the boost is something and fits in the stake
+    if amount == 0 {
+        return Err(TimeFeedError::InvalidAmount);
+    }
+    let stake = state.posts[&post_id].time.checked_add(amount).ok_or(TimeFeedError::BalanceOverflow)?;
+    // Move the boost from the caller to the contract
+    let mut ledger = Ledger::load();
+    charge(&mut ledger, user_id, amount, b"boost_post")?;
+    // Update post data
+    let post = state.posts.get_mut(&post_id).unwrap();
+    post.time = stake;
+    post.updated_at = env::block_timestamp();
+    // Record the boost
+    let boosted = state.boosts.entry(post_id).or_default().entry(user_id).or_insert(0);
+    *boosted = boosted.saturating_add(amount);
+    // Commit post state and the staged ledger changes together
+    env::commit_state(state);
+    ledger.commit();
+    // Return the new stake
+    env::ret(stake.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for letting boosters withdraw their share of a post's stake.
+// Only the author can change it. While it is on, the author cannot withdraw the
+// part of the stake boosters can claim. Once the post has boosts it cannot be
+// turned off again, since boosters staked on the promise of getting their share back.
+pub fn set_boost_sharing(session: Option<H256>, post_id: H256, shared: bool) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let mut state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if !state.settle_post(&post_id, env::block_timestamp()) {
+        return Err(TimeFeedError::PostNotFound);
+    }
+    // Check if post is by the caller
+    if state.posts[&post_id].user_id != user_id {
+        return Err(TimeFeedError::NotAuthor);
+    }
+    // Check if boosters are relying on sharing staying on
+    let boosted = state.boosts.get(&post_id).is_some_and(|boosters| !boosters.is_empty());
+    if !shared && boosted && state.shared_boosts.contains(&post_id) {
+        return Err(TimeFeedError::BoostSharingLocked);
+    }
+    if shared {
+        state.shared_boosts.insert(post_id);
+    } else {
+        state.shared_boosts.remove(&post_id);
+    }
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for a booster taking back their share of a post's stake.
+// The claim is paid out minus the withdrawal fee, and the same minimum stake
+// rule as for authors applies: if paying the whole claim would leave the post
+// below the minimum stake, only the part down to the minimum is paid and the
+// rest stays claimable. Returns the amount paid out.
+pub fn withdraw_boost(session: Option<H256>, post_id: H256) -> Result<(), TimeFeedError> {
+    let user_id = authenticate(session)?;
+    let mut state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if !state.settle_post(&post_id, env::block_timestamp()) {
+        return Err(TimeFeedError::PostNotFound);
+    }
+    // Check if the author shares withdrawals and the caller has boosted the post
+    if !state.shared_boosts.contains(&post_id) {
+        return Err(TimeFeedError::BoostNotShared);
+    }
+    let claim = match state.boost_claims(&post_id).into_iter().find(|(booster, _)| *booster == user_id) {
+        Some((_, claim)) => claim,
+        None => return Err(TimeFeedError::NotBooster),
+    };
+    // Check if the claim can be withdrawn, in full or down to the minimum stake
+    let time_cost = state.fees.withdraw;
+    if claim <= time_cost {
+        return Err(TimeFeedError::InvalidAmount);
+    }
+    let stake = state.posts[&post_id].time;
+    let amount = if stake - claim > 0 && stake - claim < state.fees.min_remaining_stake {
+        stake.saturating_sub(state.fees.min_remaining_stake)
+    } else {
+        claim
+    };
+    if amount <= time_cost {
+        return Err(TimeFeedError::StakeBelowMinimum);
+    }
+    let remaining = stake - amount;
+    // Pay the withdrawn amount, minus the fee, out to the booster
+    let mut ledger = Ledger::load();
+    pay_out(&mut ledger, user_id, amount - time_cost, b"withdraw_boost")?;
+    // Update post data
+    let post = state.posts.get_mut(&post_id).unwrap();
+    post.time = remaining;
+    post.updated_at = env::block_timestamp();
+    // Take what was paid off the booster's record, in proportion to their claim.
+    // Rounding up means a booster never ends up with more to claim than before.
+    let boosters = state.boosts.get_mut(&post_id).unwrap();
+    let boosted = boosters[&user_id];
+    let used = (amount as u128 * boosted as u128).div_ceil(claim as u128) as u64;
+    if used >= boosted {
+        boosters.remove(&user_id);
+    } else {
+        boosters.insert(user_id, boosted - used);
+    }
+    // Expire the post once all of its time has been withdrawn
+    if remaining == 0 {
+        state.expire_post(&post_id, env::block_timestamp());
+    }
+    // Commit post state and the staged ledger changes together
     env::commit_state(state);
+    ledger.commit();
+    // Return the amount paid out
+    env::ret((amount - time_cost).to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting who has boosted a post and by how much
+pub fn get_post_boosts(post_id: H256) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if post exists in state, once decay has been taken off its time
+    if state.live_post(&post_id, env::block_timestamp()).is_none() {
+        return Err(TimeFeedError::PostNotFound);
+    }
+    // Return boosts, by account
+    let boosters = state.boosts.get(&post_id).map(|boosters| boosters.iter().map(|(booster, amount)| (*booster, *amount)).collect()).unwrap_or_default();
+    let boosts = PostBoosts { shared: state.shared_boosts.contains(&post_id), boosters: boosters };
+    env::ret(boosts.to_bytes().unwrap());
+    Ok(())
+}
+
+// Drain the time a post has lost to decay since it was last decayed.
+// Each decay setting is charged over the time it was in force, so changing the
+// settings never forgives decay that had already built up. Only whole intervals
+// are charged: the remainder carries over while a setting stays in force, and is
+// dropped once the setting is replaced.
+fn apply_decay(post: &mut Post, history: &[DecayConfig], now: u64) {
+    // Skip settings that had been replaced before the post was last decayed
+    let first = history.partition_point(|decay| decay.since <= post.decayed_at).saturating_sub(1);
+    for (index, decay) in history.iter().enumerate().skip(first) {
+        let replaced_at = history.get(index + 1).map(|next| next.since);
+        let start = post.decayed_at.max(decay.since);
+        let end = replaced_at.unwrap_or(now).min(now);
+        if end <= start {
+            continue;
+        }
+        let intervals = if decay.rate == 0 { 0 } else { (end - start) / decay.interval };
+        post.time = post.time.saturating_sub(intervals.saturating_mul(decay.rate));
+        post.decayed_at = match replaced_at {
+            Some(_) => end,
+            None => start + intervals * decay.interval,
+        };
+    }
+}
+
+// Define function for sweeping decayed posts.
+// Meant to be called from a timer or heartbeat. Each call decays up to `limit`
+// posts, picking up where the previous call stopped, and removes every post
+// whose time has run out. Returns the number of posts removed.
+pub fn sweep_expired_posts(limit: u32) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(TimeFeedError::InvalidPageSize);
+    }
+    let now = env::block_timestamp();
+    // Pick the next batch of posts after the sweep cursor, wrapping around at the end
+    let mut batch: Vec<H256> = state.posts.range(after_cursor(&state.sweep_cursor.map(PageCursor)))
+        .map(|(post_id, _)| *post_id)
+        .take(limit as usize)
+        .collect();
+    if batch.len() < limit as usize {
+        state.sweep_cursor = None;
+        let remaining = limit as usize - batch.len();
+        let wrapped: Vec<H256> = state.posts.keys().take(remaining).filter(|post_id| !batch.contains(post_id)).cloned().collect();
+        batch.extend(wrapped);
+    } else {
+        state.sweep_cursor = batch.last().cloned();
+    }
+    // Decay each post and collect the ones that ran out of time
+    let mut expired = Vec::new();
+    for post_id in batch {
+        if !state.settle_post(&post_id, now) {
+            expired.push(post_id);
+        }
+    }
+    // Remove expired posts and let listeners know
+    for post_id in expired.iter() {
+        state.expire_post(post_id, now);
+    }
+    env::commit_state(state);
+    // Return number of posts removed
+    env::ret((expired.len() as u64).to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for setting the time decay settings
+pub fn set_decay_config(rate: u64, interval: u64) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Check if caller is an admin
+    if !state.admins.contains(&env::predecessor_account_id()) {
+        return Err(TimeFeedError::NotAdmin);
+    }
+    // Decay needs an interval to be charged over
+    if rate > 0 && interval == 0 {
+        return Err(TimeFeedError::InvalidDecayConfig);
+    }
+    // Keep the settings being replaced, so decay built up under them is still charged.
+    // Settings replaced within the same second were never in force and are dropped.
+    let now = env::block_timestamp();
+    if state.decay_history.last().is_some_and(|decay| decay.since == now) {
+        state.decay_history.pop();
+    }
+    state.decay_history.push(DecayConfig { rate: rate, interval: interval, since: now });
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for getting the time decay settings
+pub fn get_decay_config() -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Return decay settings
+    env::ret(state.decay_history.last().cloned().unwrap_or_default().to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for setting the fee schedule.
+// Every fee and stake must be at most `MAX_FEE`, and a new post must start with
+// at least the minimum stake a post can be left with. The initial stake comes out
+// of the creation fee, so it can be no more than that fee.
+pub fn set_fee_schedule(fees: FeeSchedule) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    let caller = env::predecessor_account_id();
+    // Check if caller is an admin
+    if !state.admins.contains(&caller) {
+        return Err(TimeFeedError::NotAdmin);
+    }
+    // Check the schedule is within bounds
+    let amounts = [
+        fees.create_post,
+        fees.like_post,
+        fees.dislike_post,
+        fees.withdraw,
+        fees.initial_stake,
+        fees.min_remaining_stake,
+        fees.like_stake,
+        fees.dislike_penalty,
+    ];
+    if amounts.iter().any(|amount| *amount > MAX_FEE) {
+        return Err(TimeFeedError::InvalidFeeSchedule);
+    }
+    if fees.min_remaining_stake == 0 || fees.initial_stake < fees.min_remaining_stake {
+        return Err(TimeFeedError::InvalidFeeSchedule);
+    }
+    if fees.initial_stake > fees.create_post {
+        return Err(TimeFeedError::InvalidFeeSchedule);
+    }
+    // Update fee schedule and keep a record of the change
+    state.fee_history.push(FeeChange { fees: fees.clone(), changed_by: caller, changed_at: env::block_timestamp() });
+    state.fees = fees;
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for getting the fee schedule
+pub fn get_fee_schedule() -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Return fee schedule
+    env::ret(state.fees.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting every change made to the fee schedule, oldest first
+pub fn get_fee_history() -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Return fee history
+    env::ret(state.fee_history.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for setting how reaction fees are shared
+pub fn set_reaction_split(author_bps: u16, treasury_bps: u16, burn_bps: u16) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Check if caller is an admin
+    if !state.admins.contains(&env::predecessor_account_id()) {
+        return Err(TimeFeedError::NotAdmin);
+    }
+    // Check the shares add up to the whole fee
+    if author_bps as u32 + treasury_bps as u32 + burn_bps as u32 != 10000 {
+        return Err(TimeFeedError::InvalidSplit);
+    }
+    state.reaction_split = ReactionSplit { author_bps: author_bps, treasury_bps: treasury_bps, burn_bps: burn_bps };
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for getting how reaction fees are shared
+pub fn get_reaction_split() -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Return reaction split
+    env::ret(state.reaction_split.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting the total an author has earned from reactions
+pub fn get_author_earnings(account_id: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Return earnings
+    env::ret(state.earnings.get(&account_id).unwrap_or(&0).to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for adding a contract admin.
+// A fresh contract starts with the contract account as its only admin; after that
+// only admins can add admins.
+pub fn add_admin(account_id: account::Id) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Check if caller is an admin
+    if !state.admins.contains(&env::predecessor_account_id()) {
+        return Err(TimeFeedError::NotAdmin);
+    }
+    // Add admin to state
+    state.admins.insert(account_id);
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for removing a contract admin
+pub fn remove_admin(account_id: account::Id) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Check if caller is an admin
+    if !state.admins.contains(&env::predecessor_account_id()) {
+        return Err(TimeFeedError::NotAdmin);
+    }
+    // Remove admin from state
+    state.admins.remove(&account_id);
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for creating a new category
+pub fn create_category(slug: String, name: String) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    let caller = env::predecessor_account_id();
+    // Check if caller is an admin
+    if !state.admins.contains(&caller) {
+        return Err(TimeFeedError::NotAdmin);
+    }
+    let category = Category::new(&slug)?;
+    // Check if category already exists
+    if state.categories.contains_key(&category) {
+        return Err(TimeFeedError::CategoryExists);
+    }
+    // Add new category to state
+    state.categories.insert(category.clone(), CategoryInfo {
+        name: name,
+        archived: false,
+        created_by: caller,
+    });
+    env::commit_state(state);
+    // Return the category id
+    env::ret(category.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for renaming a category
+pub fn rename_category(category: Category, name: String) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Check if caller is an admin
+    if !state.admins.contains(&env::predecessor_account_id()) {
+        return Err(TimeFeedError::NotAdmin);
+    }
+    // Check if category exists in registry
+    let info = match state.categories.get_mut(&category) {
+        Some(info) => info,
+        None => return Err(TimeFeedError::CategoryNotFound),
+    };
+    // Update category name
+    info.name = name;
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for archiving a category.
+// Archived categories keep their posts and can still be queried, but no new
+// posts can be created in them.
+pub fn archive_category(category: Category) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Check if caller is an admin
+    if !state.admins.contains(&env::predecessor_account_id()) {
+        return Err(TimeFeedError::NotAdmin);
+    }
+    // Check if category exists in registry
+    let info = match state.categories.get_mut(&category) {
+        Some(info) => info,
+        None => return Err(TimeFeedError::CategoryNotFound),
+    };
+    // Mark category as archived
+    info.archived = true;
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for getting a category
+pub fn get_category(category: Category) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if category exists in registry
+    let info = match state.categories.get(&category) {
+        Some(info) => info,
+        None => return Err(TimeFeedError::CategoryNotFound),
+    };
+    // Return category data
+    env::ret(info.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting all categories, sorted by id
+pub fn get_categories(include_archived: bool) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Collect categories, skipping archived ones unless asked for
+    let mut categories: Vec<(Category, CategoryInfo)> = state.categories.iter()
+        .filter(|(_, info)| include_archived || !info.archived)
+        .map(|(category, info)| (category.clone(), info.clone()))
+        .collect();
+    categories.sort_by(|a, b| a.0.cmp(&b.0));
+    // Return categories
+    env::ret(categories.to_bytes().unwrap());
+    Ok(())
 }
 
 
@@ -618,4 +1785,582 @@ a post. If they have used up their free post then they have to spend $TIME token
  create post otherwise they can use the free post.
 */
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Host::{CallError, InMemoryHost};
+    use crate::Host::testing::{account, decode, new_host, CONTRACT};
+    use crate::TokenManagement;
+    use crate::UserManagement::{block_user, register_user};
+
+    // Install a host with a "news" category
+    fn setup() -> InMemoryHost {
+        let host = new_host();
+        host.set_caller(account(CONTRACT));
+        host.call(|| create_category(String::from("news"), String::from("News"))).unwrap();
+        host
+    }
+
+    // Mint $TIME to a test account
+    fn fund(host: &InMemoryHost, n: u8, amount: u64) {
+        host.set_caller(account(CONTRACT));
+        host.call(|| TokenManagement::mint(account(n), amount, None)).unwrap();
+    }
+
+    // Run an entry point directly, so the host does not roll back a failure, and
+    // check it fails with the given error without changing any stored state
+    fn assert_fails_cleanly<F: FnOnce() -> Result<(), TimeFeedError>>(host: &InMemoryHost, error: TimeFeedError, f: F) {
+        let before = host.stored_state();
+        assert_eq!(f(), Err(error));
+        assert_eq!(host.stored_state(), before);
+    }
+
+    // Create a post in "news" as a test account, returning its id
+    fn post_as(host: &InMemoryHost, n: u8) -> H256 {
+        host.set_caller(account(n));
+        let news = Category::new("news").unwrap();
+        decode(host.call(|| create_post(None, news, String::from("Heading"), String::from("Content"), H256::zero())).unwrap())
+    }
+
+    #[test]
+    fn register_post_and_like() {
+        let host = setup();
+        host.set_caller(account(1));
+        host.call(|| register_user(String::from("Alice"), String::from("alice"), String::from("alice@example.com"), b"correct horse".to_vec())).unwrap();
+        fund(&host, 1, 10);
+        fund(&host, 2, 10);
+        let post_id = post_as(&host, 1);
+        host.set_caller(account(2));
+        host.call(|| like_post(None, post_id)).unwrap();
+        // The like is counted, adds to the stake and is paid for by the liker
+        let state: State = env::state();
+        let post = &state.posts[&post_id];
+        assert_eq!(post.user_id, account(1));
+        assert_eq!(post.likes, 1);
+        assert_eq!(post.time, 6);
+        assert_eq!(state.reaction_of(&post_id, &account(2)), Some(Reaction::Like));
+        // A 1 $TIME fee is too small to split, so the author gets all of it
+        assert_eq!(TokenManagement::balance_of(account(1)), 6);
+        assert_eq!(TokenManagement::balance_of(account(2)), 8);
+        assert_eq!(TokenManagement::balance_of(account(CONTRACT)), 6);
+        // Liking twice is rejected
+        assert_eq!(host.call(|| like_post(None, post_id)), Err(CallError::Failed(TimeFeedError::AlreadyVoted)));
+    }
+
+    #[test]
+    fn likes_stay_backed_whatever_the_split() {
+        let host = setup();
+        host.call(|| set_reaction_split(10000, 0, 0)).unwrap();
+        fund(&host, 1, 10);
+        fund(&host, 2, 10);
+        let post_id = post_as(&host, 1);
+        host.set_caller(account(2));
+        host.call(|| like_post(None, post_id)).unwrap();
+        // With no treasury share the contract still holds the like's stake
+        let state: State = env::state();
+        assert_eq!(state.posts[&post_id].time, 6);
+        assert_eq!(TokenManagement::balance_of(account(CONTRACT)), 6);
+        // and can pay all of the post's time back to the author
+        host.set_caller(account(1));
+        host.call(|| delete_post(None, post_id)).unwrap();
+        assert_eq!(TokenManagement::balance_of(account(CONTRACT)), 0);
+        assert_eq!(TokenManagement::balance_of(account(1)), 12);
+    }
+
+    #[test]
+    fn fee_schedule_cannot_stake_more_than_it_charges() {
+        let host = setup();
+        // A post starting with more time than it was charged would be unbacked
+        let fees = FeeSchedule { create_post: 5, initial_stake: 6, ..FeeSchedule::default() };
+        assert_eq!(host.call(|| set_fee_schedule(fees)), Err(CallError::Failed(TimeFeedError::InvalidFeeSchedule)));
+        let fees = FeeSchedule { create_post: 6, initial_stake: 6, ..FeeSchedule::default() };
+        host.call(|| set_fee_schedule(fees)).unwrap();
+    }
+
+    #[test]
+    fn reaction_amounts_come_from_the_fee_schedule() {
+        let host = setup();
+        fund(&host, 1, 10);
+        fund(&host, 2, 10);
+        let post_id = post_as(&host, 1);
+        // The like stake and dislike penalty are reported and bounded like every fee
+        host.set_caller(account(CONTRACT));
+        let fees = FeeSchedule { dislike_penalty: MAX_FEE + 1, ..FeeSchedule::default() };
+        assert_eq!(host.call(|| set_fee_schedule(fees)), Err(CallError::Failed(TimeFeedError::InvalidFeeSchedule)));
+        let fees = FeeSchedule { like_stake: 3, dislike_penalty: 2, ..FeeSchedule::default() };
+        host.call(|| set_fee_schedule(fees)).unwrap();
+        let fees: FeeSchedule = decode(host.call(get_fee_schedule).unwrap());
+        assert_eq!((fees.like_stake, fees.dislike_penalty), (3, 2));
+        // A like costs the fee plus the stake, which goes on the post
+        host.set_caller(account(2));
+        host.call(|| like_post(None, post_id)).unwrap();
+        assert_eq!(TokenManagement::balance_of(account(2)), 6);
+        assert_eq!(env::state::<State>().posts[&post_id].time, 8);
+        // Taking a reaction back reverses what it did, even after the schedule changed
+        host.set_caller(account(CONTRACT));
+        host.call(|| set_fee_schedule(FeeSchedule::default())).unwrap();
+        host.set_caller(account(2));
+        host.call(|| dislike_post(None, post_id)).unwrap();
+        assert_eq!(env::state::<State>().posts[&post_id].time, 4);
+        host.call(|| unvote(None, post_id)).unwrap();
+        assert_eq!(env::state::<State>().posts[&post_id].time, 5);
+    }
+
+    // Set up a post by account 1 with boost sharing on, boosted by 4 by account 3,
+    // and account 2 holding $TIME to react with
+    fn boosted_post() -> (InMemoryHost, H256) {
+        let host = setup();
+        fund(&host, 1, 10);
+        fund(&host, 2, 10);
+        fund(&host, 3, 10);
+        let post_id = post_as(&host, 1);
+        host.call(|| set_boost_sharing(None, post_id, true)).unwrap();
+        host.set_caller(account(3));
+        host.call(|| boost_post(None, post_id, 4)).unwrap();
+        (host, post_id)
+    }
+
+    #[test]
+    fn failed_transfers_change_nothing() {
+        use TimeFeedError::{BalanceOverflow, InsufficientBalance};
+        // Each case is the caller, an account whose balance is forced, that
+        // balance, and the error the entry point must fail with. Post memos are
+        // fixed and fit the ledger's limit, so `MemoTooLong` cannot come up here.
+        type Case = (u8, u8, u64, TimeFeedError, fn(H256) -> Result<(), TimeFeedError>);
+        let cases: Vec<Case> = vec![
+            (2, 2, 0, InsufficientBalance, |post_id| like_post(None, post_id)),
+            (2, 2, 0, InsufficientBalance, |post_id| dislike_post(None, post_id)),
+            (2, 2, 0, InsufficientBalance, |post_id| boost_post(None, post_id, 3)),
+            (1, CONTRACT, 0, InsufficientBalance, |post_id| withdraw_time_by_user_and_post(None, post_id, 3)),
+            (3, CONTRACT, 0, InsufficientBalance, |post_id| withdraw_boost(None, post_id)),
+            (2, CONTRACT, u64::MAX, BalanceOverflow, |post_id| like_post(None, post_id)),
+            (2, 1, u64::MAX, BalanceOverflow, |post_id| dislike_post(None, post_id)),
+            (2, CONTRACT, u64::MAX, BalanceOverflow, |post_id| boost_post(None, post_id, 3)),
+            (1, 1, u64::MAX, BalanceOverflow, |post_id| withdraw_time_by_user_and_post(None, post_id, 3)),
+            (3, 3, u64::MAX, BalanceOverflow, |post_id| withdraw_boost(None, post_id)),
+        ];
+        for (caller, holder, balance, error, entry_point) in cases {
+            let (host, post_id) = boosted_post();
+            TokenManagement::set_balance(account(holder), balance);
+            host.set_caller(account(caller));
+            assert_fails_cleanly(&host, error, || entry_point(post_id));
+        }
+        // Not enough $TIME to post
+        let host = setup();
+        host.set_caller(account(2));
+        let news = Category::new("news").unwrap();
+        assert_fails_cleanly(&host, InsufficientBalance, || create_post(None, news, String::from("Heading"), String::from("Content"), H256::zero()));
+    }
+
+    #[test]
+    fn failure_after_a_payout_changes_nothing() {
+        let host = setup();
+        fund(&host, 1, 10);
+        fund(&host, 2, 10);
+        let post_id = post_as(&host, 1);
+        host.call(|| set_boost_sharing(None, post_id, true)).unwrap();
+        host.set_caller(account(2));
+        host.call(|| boost_post(None, post_id, 2)).unwrap();
+        // The booster's share is staged before the contract runs short of the
+        // author's refund, and neither may land
+        TokenManagement::set_balance(account(CONTRACT), 3);
+        host.set_caller(account(1));
+        assert_fails_cleanly(&host, TimeFeedError::InsufficientBalance, || delete_post(None, post_id));
+        assert_eq!(TokenManagement::balance_of(account(2)), 8);
+    }
+
+    #[test]
+    fn boost_sharing_stays_on_once_boosted() {
+        let host = setup();
+        fund(&host, 1, 10);
+        fund(&host, 2, 10);
+        let post_id = post_as(&host, 1);
+        host.call(|| set_boost_sharing(None, post_id, true)).unwrap();
+        host.set_caller(account(2));
+        host.call(|| boost_post(None, post_id, 2)).unwrap();
+        // The author cannot take back the booster's claim by turning sharing off
+        host.set_caller(account(1));
+        assert_eq!(host.call(|| set_boost_sharing(None, post_id, false)), Err(CallError::Failed(TimeFeedError::BoostSharingLocked)));
+        host.set_caller(account(2));
+        host.call(|| withdraw_boost(None, post_id)).unwrap();
+        // With the boost withdrawn there is nothing left to protect
+        host.set_caller(account(1));
+        host.call(|| set_boost_sharing(None, post_id, false)).unwrap();
+    }
+
+    #[test]
+    fn boosters_take_what_they_can_down_to_the_minimum_stake() {
+        let host = setup();
+        for n in 1..=3 {
+            fund(&host, n, 10);
+        }
+        let post_id = post_as(&host, 1);
+        host.call(|| set_boost_sharing(None, post_id, true)).unwrap();
+        host.set_caller(account(3));
+        host.call(|| boost_post(None, post_id, 2)).unwrap();
+        host.set_caller(account(2));
+        host.call(|| boost_post(None, post_id, 4)).unwrap();
+        // Boosters are listed by account, whatever order they boosted in
+        let boosts: PostBoosts = decode(host.call(|| get_post_boosts(post_id)).unwrap());
+        assert_eq!(boosts.boosters, vec![(account(2), 4), (account(3), 2)]);
+        // The author takes 4 of the 5 that is theirs, leaving 7 at stake, so
+        // account 2's whole claim of 4 would take the post below the minimum of 5
+        host.set_caller(account(1));
+        host.call(|| withdraw_time_by_user_and_post(None, post_id, 4)).unwrap();
+        host.set_caller(account(2));
+        let paid: u64 = decode(host.call(|| withdraw_boost(None, post_id)).unwrap());
+        // Only the 2 down to the minimum is taken, minus the fee, and half the boost stays on record
+        assert_eq!((paid, TokenManagement::balance_of(account(2))), (1, 7));
+        let state: State = env::state();
+        assert_eq!(state.posts[&post_id].time, 5);
+        assert_eq!(state.boosts[&post_id][&account(2)], 2);
+        // Nothing more can come out while the post is at the minimum
+        assert_eq!(host.call(|| withdraw_boost(None, post_id)), Err(CallError::Failed(TimeFeedError::StakeBelowMinimum)));
+    }
+
+    #[test]
+    fn home_feed_skips_authors_blocked_either_way() {
+        let host = setup();
+        host.set_caller(account(1));
+        host.call(|| register_user(String::from("Alice"), String::from("alice"), String::from("alice@example.com"), b"correct horse".to_vec())).unwrap();
+        host.set_caller(account(2));
+        host.call(|| register_user(String::from("Bob"), String::from("bob"), String::from("bob@example.com"), b"battery staple".to_vec())).unwrap();
+        fund(&host, 2, 10);
+        post_as(&host, 2);
+        // Bob blocking Alice keeps Bob's posts out of Alice's feed, not just the other way round
+        host.set_caller(account(2));
+        host.call(|| block_user(account(1))).unwrap();
+        host.set_caller(account(1));
+        let news = Category::new("news").unwrap();
+        let page: FeedPage = decode(host.call(|| get_home_feed(None, vec![news.clone()], 10, None)).unwrap());
+        assert!(page.posts.is_empty());
+    }
+
+    // Small xorshift generator, so a failing sequence can be replayed from its seed
+    struct Rng(u64);
+
+    impl Rng {
+        // Get a number below `bound`
+        fn below(&mut self, bound: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % bound
+        }
+    }
+
+    // Check the counters of every live post match its reactions, that no post is
+    // left without time, and that the contract holds enough $TIME to back every stake
+    fn check_invariants(posts: &[H256], context: &str) {
+        let state: State = env::state();
+        let mut staked: u64 = 0;
+        for post_id in posts {
+            let post = match state.posts.get(post_id) {
+                Some(post) => post,
+                None => continue,
+            };
+            let count = |reaction: Reaction| state.reactions.get(post_id).map_or(0, |voters| voters.values().filter(|vote| vote.reaction == reaction).count() as u64);
+            assert_eq!(post.likes, count(Reaction::Like), "likes out of step {}", context);
+            assert_eq!(post.dislikes, count(Reaction::Dislike), "dislikes out of step {}", context);
+            assert!(post.time > 0, "post left without time {}", context);
+            staked += post.time;
+        }
+        assert!(TokenManagement::balance_of(account(CONTRACT)) >= staked, "stakes not backed {}", context);
+    }
+
+    #[test]
+    fn random_reactions_keep_posts_consistent() {
+        for seed in 1..=25u64 {
+            let host = setup();
+            let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+            for n in 1..=4 {
+                fund(&host, n, 1000);
+            }
+            let posts: Vec<H256> = (1..=3).map(|n| post_as(&host, n)).collect();
+            // Some authors share withdrawals with their boosters
+            for (n, post_id) in posts.iter().enumerate() {
+                if rng.below(2) == 0 {
+                    host.set_caller(account(n as u8 + 1));
+                    host.call(|| set_boost_sharing(None, *post_id, true)).unwrap();
+                }
+            }
+            for step in 0..200 {
+                let caller = rng.below(4) as u8 + 1;
+                let post_id = posts[rng.below(posts.len() as u64) as usize];
+                let amount = rng.below(12);
+                host.set_caller(account(caller));
+                let result = match rng.below(7) {
+                    0 => host.call(|| like_post(None, post_id)),
+                    1 => host.call(|| dislike_post(None, post_id)),
+                    2 => host.call(|| unvote(None, post_id)),
+                    3 => host.call(|| withdraw_time_by_user_and_post(None, post_id, amount)),
+                    4 => host.call(|| boost_post(None, post_id, amount)),
+                    5 => host.call(|| withdraw_boost(None, post_id)),
+                    // Reactions must undo what they did, not what the schedule says now
+                    _ => {
+                        let fees = FeeSchedule { like_stake: rng.below(4), dislike_penalty: rng.below(4), ..FeeSchedule::default() };
+                        host.set_caller(account(CONTRACT));
+                        host.call(|| set_fee_schedule(fees))
+                    }
+                };
+                let context = format!("at seed {} step {}", seed, step);
+                if let Err(CallError::Panicked(message)) = result {
+                    panic!("call panicked {}: {}", context, message);
+                }
+                check_invariants(&posts, &context);
+            }
+        }
+    }
+
+    #[test]
+    fn contract_account_is_the_first_admin_and_minter() {
+        let host = new_host();
+        // Nobody else can make themselves an admin or the minter on a fresh contract
+        host.set_caller(account(1));
+        assert_eq!(host.call(|| add_admin(account(1))), Err(CallError::Failed(TimeFeedError::NotAdmin)));
+        assert_eq!(host.call(|| TokenManagement::set_minter(account(1))), Err(CallError::Failed(TimeFeedError::NotMinter)));
+        // The contract account can add admins and hand on the minter role
+        host.set_caller(account(CONTRACT));
+        host.call(|| add_admin(account(1))).unwrap();
+        host.call(|| TokenManagement::set_minter(account(1))).unwrap();
+        let state: State = env::state();
+        assert!(state.admins.contains(&account(1)));
+        assert_eq!(host.call(|| TokenManagement::mint(account(1), 1, None)), Err(CallError::Failed(TimeFeedError::NotMinter)));
+    }
+
+    // Check every index lists exactly the stored posts that belong in it
+    fn check_indexes() {
+        let state: State = env::state();
+        let mut by_author: HashMap<account::Id, BTreeSet<H256>> = HashMap::new();
+        let mut by_category: HashMap<Category, BTreeSet<H256>> = HashMap::new();
+        let mut by_author_and_category: HashMap<(account::Id, Category), BTreeSet<H256>> = HashMap::new();
+        let mut by_created_at = BTreeSet::new();
+        for (post_id, post) in state.posts.iter() {
+            by_author.entry(post.user_id).or_default().insert(*post_id);
+            by_category.entry(post.category.clone()).or_default().insert(*post_id);
+            by_author_and_category.entry((post.user_id, post.category.clone())).or_default().insert(*post_id);
+            by_created_at.insert((post.created_at, *post_id));
+        }
+        assert_eq!(state.posts_by_author, by_author);
+        assert_eq!(state.posts_by_category, by_category);
+        assert_eq!(state.posts_by_author_and_category, by_author_and_category);
+        assert_eq!(state.posts_by_created_at, by_created_at);
+    }
+
+    #[test]
+    fn indexes_follow_posts_until_they_are_gone() {
+        let host = setup();
+        host.call(|| create_category(String::from("sport"), String::from("Sport"))).unwrap();
+        fund(&host, 1, 20);
+        let post_ids: Vec<H256> = (0..4).map(|_| post_as(&host, 1)).collect();
+        check_indexes();
+        // Moving a post to another category moves it between the category indexes
+        let sport = Category::new("sport").unwrap();
+        host.call(|| edit_post(None, post_ids[0], sport.clone(), String::from("Heading"), String::from("Content"), H256::zero())).unwrap();
+        check_indexes();
+        let page: PostPage = decode(host.call(|| get_posts_by_user_and_category_page(account(1), sport, 10, None)).unwrap());
+        assert_eq!(page.posts.len(), 1);
+        // Deleting, withdrawing everything and running out of time all drop a post
+        host.call(|| delete_post(None, post_ids[1])).unwrap();
+        check_indexes();
+        host.call(|| withdraw_time_by_user_and_post(None, post_ids[2], 5)).unwrap();
+        check_indexes();
+        host.set_caller(account(CONTRACT));
+        host.call(|| set_decay_config(5, 60)).unwrap();
+        host.advance_time(60);
+        host.call(|| sweep_expired_posts(10)).unwrap();
+        check_indexes();
+        let state: State = env::state();
+        assert!(state.posts.is_empty() && state.posts_by_author.is_empty() && state.posts_by_created_at.is_empty());
+    }
+
+    #[test]
+    fn pages_continue_from_the_cursor() {
+        let host = setup();
+        fund(&host, 1, 15);
+        let mut post_ids: Vec<H256> = (0..3).map(|_| post_as(&host, 1)).collect();
+        post_ids.sort();
+        // Pages come in post id order and pick up after the cursor
+        let first: PostPage = decode(host.call(|| get_all_posts_page(2, None)).unwrap());
+        let ids: Vec<H256> = first.posts.iter().map(|(post_id, _)| *post_id).collect();
+        assert_eq!(ids, post_ids[..2]);
+        let last: PostPage = decode(host.call(|| get_all_posts_page(2, first.next_cursor)).unwrap());
+        let ids: Vec<H256> = last.posts.iter().map(|(post_id, _)| *post_id).collect();
+        assert_eq!(ids, post_ids[2..]);
+        assert!(last.next_cursor.is_none());
+        // A page that ends exactly on the last post has no cursor either
+        let news = Category::new("news").unwrap();
+        let page: PostPage = decode(host.call(|| get_posts_by_category_page(news, 3, None)).unwrap());
+        assert_eq!(page.posts.len(), 3);
+        assert!(page.next_cursor.is_none());
+        assert_eq!(host.call(|| get_all_posts_page(0, None)), Err(CallError::Failed(TimeFeedError::InvalidPageSize)));
+        assert_eq!(host.call(|| get_all_posts_page(MAX_PAGE_SIZE + 1, None)), Err(CallError::Failed(TimeFeedError::InvalidPageSize)));
+    }
+
+    #[test]
+    fn post_ids_are_unique_and_reproducible() {
+        // Create two identical posts, delete the first and post it again
+        let run = || -> Vec<H256> {
+            let host = setup();
+            fund(&host, 1, 20);
+            let mut post_ids = vec![post_as(&host, 1), post_as(&host, 1)];
+            host.call(|| delete_post(None, post_ids[0])).unwrap();
+            post_ids.push(post_as(&host, 1));
+            post_ids
+        };
+        let post_ids = run();
+        // Same content never shares an id, even once the earlier post is gone
+        let unique: HashSet<H256> = post_ids.iter().cloned().collect();
+        assert_eq!(unique.len(), post_ids.len());
+        // and the same calls on a fresh contract give the same ids
+        assert_eq!(run(), post_ids);
+    }
+
+    #[test]
+    fn creation_time_pages_stay_in_the_window() {
+        let host = setup();
+        fund(&host, 1, 15);
+        fund(&host, 2, 5);
+        let mut created = Vec::new();
+        for (n, now) in [(1, 100), (2, 200), (1, 300), (1, 400)] {
+            host.set_block_timestamp(now);
+            created.push(post_as(&host, n));
+        }
+        let ids = |page: &CreationPage| -> Vec<H256> { page.posts.iter().map(|(post_id, _)| *post_id).collect() };
+        // Oldest first, only posts created within the window
+        let page: CreationPage = decode(host.call(|| get_posts_by_creation_time(Some(150), Some(300), None, None, false, 10, None)).unwrap());
+        assert_eq!(ids(&page), created[1..3]);
+        assert!(page.next_cursor.is_none());
+        // Newest first, continuing from the cursor until the window is used up
+        let first: CreationPage = decode(host.call(|| get_posts_by_creation_time(Some(150), None, None, None, true, 2, None)).unwrap());
+        assert_eq!(ids(&first), vec![created[3], created[2]]);
+        let last: CreationPage = decode(host.call(|| get_posts_by_creation_time(Some(150), None, None, None, true, 2, first.next_cursor)).unwrap());
+        assert_eq!(ids(&last), vec![created[1]]);
+        assert!(last.next_cursor.is_none());
+        // Filtering by author skips the other author's posts
+        let page: CreationPage = decode(host.call(|| get_posts_by_creation_time(None, None, Some(account(1)), None, false, 10, None)).unwrap());
+        assert_eq!(ids(&page), vec![created[0], created[2], created[3]]);
+        // An empty window is not an error
+        let page: CreationPage = decode(host.call(|| get_posts_by_creation_time(Some(300), Some(200), None, None, false, 10, None)).unwrap());
+        assert!(page.posts.is_empty());
+    }
+
+    #[test]
+    fn decay_is_applied_before_paying_out() {
+        let host = setup();
+        fund(&host, 1, 10);
+        let post_id = post_as(&host, 1);
+        host.set_caller(account(CONTRACT));
+        host.call(|| set_decay_config(1, 10)).unwrap();
+        host.advance_time(30);
+        // The post shows the time it has left, not the time it was last swept with
+        let post: Post = decode(host.call(|| get_post(post_id)).unwrap());
+        assert_eq!(post.time, 2);
+        // The author cannot withdraw time the post has already lost
+        host.set_caller(account(1));
+        assert_eq!(host.call(|| withdraw_time_by_user_and_post(None, post_id, 5)), Err(CallError::Failed(TimeFeedError::InvalidAmount)));
+        // Once it has run out there is nothing left to delete or refund
+        host.advance_time(20);
+        assert_eq!(host.call(|| delete_post(None, post_id)), Err(CallError::Failed(TimeFeedError::PostNotFound)));
+        assert_eq!(TokenManagement::balance_of(account(1)), 5);
+    }
 
+    #[test]
+    fn changing_decay_settings_charges_what_was_owed() {
+        let host = setup();
+        fund(&host, 1, 10);
+        let post_id = post_as(&host, 1);
+        host.set_caller(account(CONTRACT));
+        host.call(|| set_decay_config(1, 10)).unwrap();
+        host.advance_time(25);
+        // Turning decay off still charges the two intervals owed under the old rate
+        host.call(|| set_decay_config(0, 0)).unwrap();
+        host.advance_time(100);
+        let before = host.stored_state();
+        let post: Post = decode(host.call(|| get_post(post_id)).unwrap());
+        assert_eq!(post.time, 3);
+        // Decay is not an edit, and reading a post never writes it back
+        assert_eq!(post.updated_at, post.created_at);
+        assert_eq!(host.stored_state(), before);
+    }
+
+    #[test]
+    fn run_out_posts_are_only_expired_by_the_sweep() {
+        let host = setup();
+        fund(&host, 1, 10);
+        let post_id = post_as(&host, 1);
+        host.set_caller(account(CONTRACT));
+        host.call(|| set_decay_config(5, 10)).unwrap();
+        host.advance_time(10);
+        // Touching a post that has run out fails without emitting an event it could not commit
+        let events = host.events().len();
+        host.set_caller(account(2));
+        assert_fails_cleanly(&host, TimeFeedError::PostNotFound, || like_post(None, post_id));
+        assert_eq!(host.call(|| get_post(post_id)), Err(CallError::Failed(TimeFeedError::PostNotFound)));
+        assert_eq!(host.events().len(), events);
+        // The sweep removes it and reports the expiry once
+        let removed: u64 = decode(host.call(|| sweep_expired_posts(10)).unwrap());
+        assert_eq!(removed, 1);
+        assert_eq!(host.events().len(), events + 1);
+        let removed: u64 = decode(host.call(|| sweep_expired_posts(10)).unwrap());
+        assert_eq!(removed, 0);
+        assert_eq!(host.events().len(), events + 1);
+    }
+
+    #[test]
+    fn reactions_switch_and_can_be_taken_back() {
+        let host = setup();
+        fund(&host, 1, 10);
+        fund(&host, 2, 10);
+        let post_id = post_as(&host, 1);
+        host.set_caller(account(2));
+        host.call(|| like_post(None, post_id)).unwrap();
+        // Disliking a liked post takes the like back before adding the dislike
+        host.call(|| dislike_post(None, post_id)).unwrap();
+        let post: Post = decode(host.call(|| get_post(post_id)).unwrap());
+        assert_eq!((post.likes, post.dislikes, post.time), (0, 1, 4));
+        let reaction: Option<Reaction> = decode(host.call(|| get_my_reaction(None, post_id)).unwrap());
+        assert_eq!(reaction, Some(Reaction::Dislike));
+        assert_eq!(decode::<u64>(host.call(|| get_total_likes_by_user(account(1))).unwrap()), 0);
+        assert_eq!(decode::<u64>(host.call(|| get_total_dislikes_by_user(account(1))).unwrap()), 1);
+        // Taking the dislike back restores the post but keeps the fees paid
+        host.call(|| unvote(None, post_id)).unwrap();
+        let post: Post = decode(host.call(|| get_post(post_id)).unwrap());
+        assert_eq!((post.likes, post.dislikes, post.time), (0, 0, 5));
+        let reaction: Option<Reaction> = decode(host.call(|| get_my_reaction(None, post_id)).unwrap());
+        assert_eq!(reaction, None);
+        assert_eq!(TokenManagement::balance_of(account(2)), 7);
+        assert_eq!(host.call(|| unvote(None, post_id)), Err(CallError::Failed(TimeFeedError::NotVoted)));
+    }
+
+    #[test]
+    fn edits_keep_the_previous_versions() {
+        let host = setup();
+        fund(&host, 1, 10);
+        let post_id = post_as(&host, 1);
+        let news = Category::new("news").unwrap();
+        // Only the author can edit, and only into a category that exists
+        host.set_caller(account(2));
+        assert_eq!(
+            host.call(|| edit_post(None, post_id, news.clone(), String::from("Mine now"), String::new(), H256::zero())),
+            Err(CallError::Failed(TimeFeedError::NotAuthor))
+        );
+        host.set_caller(account(1));
+        assert_eq!(
+            host.call(|| edit_post(None, post_id, Category::new("sport").unwrap(), String::from("Heading"), String::new(), H256::zero())),
+            Err(CallError::Failed(TimeFeedError::CategoryNotFound))
+        );
+        host.advance_time(60);
+        host.call(|| edit_post(None, post_id, news.clone(), String::from("Edited"), String::from("Content"), H256::zero())).unwrap();
+        host.advance_time(60);
+        host.call(|| edit_post(None, post_id, news.clone(), String::from("Edited again"), String::from("Content"), H256::zero())).unwrap();
+        // The history holds every replaced version, oldest first
+        let post: Post = decode(host.call(|| get_post(post_id)).unwrap());
+        assert_eq!(post.heading, "Edited again");
+        let history: Vec<PostRevision> = decode(host.call(|| get_post_history(post_id)).unwrap());
+        let headings: Vec<&str> = history.iter().map(|revision| revision.heading.as_str()).collect();
+        assert_eq!(headings, vec!["Heading", "Edited"]);
+        assert!(history[0].edited_at < history[1].edited_at);
+        assert_eq!(host.call(|| get_post_history(H256::zero())), Err(CallError::Failed(TimeFeedError::PostNotFound)));
+    }
+}
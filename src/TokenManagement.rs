@@ -0,0 +1,491 @@
+// Import necessary libraries
+use std::collections::HashMap;
+use icp_contracts::account;
+use icp_contracts::bytesrepr::{ToBytes, FromBytes};
+use crate::Host::{env, ContractState};
+use crate::Error::TimeFeedError;
+
+// Longest memo accepted on a transaction, in bytes
+const MAX_MEMO_LEN: usize = 32;
+
+// Maximum number of transactions returned in a single page
+const MAX_PAGE_SIZE: u32 = 100;
+
+// Define struct for an allowance one account has given another
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+struct Allowance {
+    amount: u64,
+    expires_at: Option<u64>,
+}
+
+// Define enum for what a ledger transaction did
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+enum Operation {
+    Mint { to: account::Id, amount: u64 },
+    Burn { from: account::Id, amount: u64 },
+    Transfer { from: account::Id, to: account::Id, amount: u64, spender: Option<account::Id> },
+    Approve { from: account::Id, spender: account::Id, amount: u64, expires_at: Option<u64> },
+}
+
+// Define struct for an entry in the transaction log
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+struct Transaction {
+    operation: Operation,
+    memo: Option<Vec<u8>>,
+    timestamp: u64,
+}
+
+// Define struct for ledger state.
+// The transaction log is not part of it: each transaction is stored under its
+// own key, so the state stays the same size however long the log gets.
+#[derive(Debug, Default, ToBytes, FromBytes)]
+struct State {
+    balances: HashMap<account::Id, u64>,
+    allowances: HashMap<(account::Id, account::Id), Allowance>,
+    total_supply: u64,
+    minter: Option<account::Id>,
+    transaction_count: u64,
+    legacy_imported: u64,
+    contract_balance_imported: bool,
+}
+
+impl ContractState for State {
+    const STATE_KEY: &'static str = "time_feed/ledger";
+
+    // A fresh ledger can only be minted by the contract account
+    fn init() -> Self {
+        State {
+            minter: Some(env::predecessor_contract_id()),
+            ..State::default()
+        }
+    }
+}
+
+impl State {
+    // Get the balance of an account
+    fn balance(&self, account_id: &account::Id) -> u64 {
+        *self.balances.get(account_id).unwrap_or(&0)
+    }
+
+    // Move $TIME between two accounts
+    fn move_balance(&mut self, from: account::Id, to: account::Id, amount: u64) -> Result<(), TimeFeedError> {
+        let from_balance = self.balance(&from);
+        if from_balance < amount {
+            return Err(TimeFeedError::InsufficientBalance);
+        }
+        let to_balance = self.balance(&to);
+        if from != to {
+            let new_to_balance = to_balance.checked_add(amount).ok_or(TimeFeedError::BalanceOverflow)?;
+            self.balances.insert(from, from_balance - amount);
+            self.balances.insert(to, new_to_balance);
+        }
+        Ok(())
+    }
+
+}
+
+// Get the key a transaction is stored under
+fn transaction_key(index: u64) -> String {
+    format!("{}/transactions/{}", State::STATE_KEY, index)
+}
+
+// Check a memo is within the length limit
+fn validate_memo(memo: &Option<Vec<u8>>) -> Result<(), TimeFeedError> {
+    if memo.as_ref().is_some_and(|memo| memo.len() > MAX_MEMO_LEN) {
+        return Err(TimeFeedError::MemoTooLong);
+    }
+    Ok(())
+}
+
+// Get the $TIME balance of an account, for other contracts
+pub(crate) fn balance_of(account_id: account::Id) -> u64 {
+    let state: State = env::state();
+    state.balance(&account_id)
+}
+
+// Overwrite a balance without any of the ledger's checks, for setting up tests
+#[cfg(test)]
+pub(crate) fn set_balance(account_id: account::Id, amount: u64) {
+    let mut state: State = env::state();
+    state.balances.insert(account_id, amount);
+    env::commit_state(state);
+}
+
+// Define struct for ledger changes staged during a call.
+// Changes, including new transactions, are only written on `commit`. The post
+// contract stages its charges and payouts and only commits once everything else
+// in the call has succeeded, so a call that fails part way never leaves a fee
+// charged without its effect.
+pub(crate) struct Ledger {
+    state: State,
+    transactions: Vec<Transaction>,
+}
+
+impl Ledger {
+    // Load the ledger to stage changes against
+    pub(crate) fn load() -> Self {
+        Ledger { state: env::state(), transactions: Vec::new() }
+    }
+
+    // Stage a transaction for the log, returning its index
+    fn record(&mut self, operation: Operation, memo: Option<Vec<u8>>) -> u64 {
+        self.transactions.push(Transaction {
+            operation: operation,
+            memo: memo,
+            timestamp: env::block_timestamp(),
+        });
+        self.state.transaction_count + self.transactions.len() as u64 - 1
+    }
+
+    // Stage a move of $TIME between two accounts, returning its transaction index
+    pub(crate) fn transfer(&mut self, from: account::Id, to: account::Id, amount: u64, memo: &[u8]) -> Result<u64, TimeFeedError> {
+        let memo = Some(memo.to_vec());
+        validate_memo(&memo)?;
+        self.state.move_balance(from, to, amount)?;
+        Ok(self.record(Operation::Transfer { from: from, to: to, amount: amount, spender: None }, memo))
+    }
+
+    // Stage a split of $TIME from one account between several recipients and a burn.
+    // Either every part is staged or, on error, none of them are.
+    pub(crate) fn distribute(&mut self, from: account::Id, payouts: &[(account::Id, u64)], burn: u64, memo: &[u8]) -> Result<(), TimeFeedError> {
+        let memo = Some(memo.to_vec());
+        validate_memo(&memo)?;
+        // Check the whole amount is there before moving any of it
+        let total = payouts.iter().try_fold(burn, |total, (_, amount)| total.checked_add(*amount)).ok_or(TimeFeedError::BalanceOverflow)?;
+        if self.state.balance(&from) < total {
+            return Err(TimeFeedError::InsufficientBalance);
+        }
+        for (to, amount) in payouts {
+            if *amount > 0 {
+                self.state.move_balance(from, *to, *amount)?;
+                self.record(Operation::Transfer { from: from, to: *to, amount: *amount, spender: None }, memo.clone());
+            }
+        }
+        if burn > 0 {
+            let balance = self.state.balance(&from);
+            self.state.balances.insert(from, balance - burn);
+            self.state.total_supply -= burn;
+            self.record(Operation::Burn { from: from, amount: burn }, memo);
+        }
+        Ok(())
+    }
+
+    // Write every staged change to the ledger, appending the new transactions to the log
+    pub(crate) fn commit(mut self) {
+        for transaction in self.transactions.iter() {
+            env::write(&transaction_key(self.state.transaction_count), transaction);
+            self.state.transaction_count += 1;
+        }
+        env::commit_state(self.state);
+    }
+}
+
+// Define function for setting the account allowed to mint $TIME.
+// A fresh ledger starts with the contract account as its minter; only the
+// current minter can hand the role on.
+pub fn set_minter(minter: account::Id) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Check if caller is the minter
+    if state.minter != Some(env::predecessor_account_id()) {
+        return Err(TimeFeedError::NotMinter);
+    }
+    state.minter = Some(minter);
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for minting new $TIME
+pub fn mint(to: account::Id, amount: u64, memo: Option<Vec<u8>>) -> Result<(), TimeFeedError> {
+    let mut ledger = Ledger::load();
+    // Check if caller is the minter
+    if ledger.state.minter != Some(env::predecessor_account_id()) {
+        return Err(TimeFeedError::NotMinter);
+    }
+    validate_memo(&memo)?;
+    // Add the new $TIME to the account and the total supply
+    let total_supply = ledger.state.total_supply.checked_add(amount).ok_or(TimeFeedError::BalanceOverflow)?;
+    let balance = ledger.state.balance(&to).checked_add(amount).ok_or(TimeFeedError::BalanceOverflow)?;
+    ledger.state.total_supply = total_supply;
+    ledger.state.balances.insert(to, balance);
+    let index = ledger.record(Operation::Mint { to: to, amount: amount }, memo);
+    ledger.commit();
+    // Return transaction index
+    env::ret(index.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for burning the caller's $TIME
+pub fn burn(amount: u64, memo: Option<Vec<u8>>) -> Result<(), TimeFeedError> {
+    let mut ledger = Ledger::load();
+    let from = env::predecessor_account_id();
+    validate_memo(&memo)?;
+    // Remove the $TIME from the account and the total supply
+    let balance = ledger.state.balance(&from);
+    if balance < amount {
+        return Err(TimeFeedError::InsufficientBalance);
+    }
+    ledger.state.balances.insert(from, balance - amount);
+    ledger.state.total_supply -= amount;
+    let index = ledger.record(Operation::Burn { from: from, amount: amount }, memo);
+    ledger.commit();
+    // Return transaction index
+    env::ret(index.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for transferring $TIME to another account
+pub fn transfer(to: account::Id, amount: u64, memo: Option<Vec<u8>>) -> Result<(), TimeFeedError> {
+    let mut ledger = Ledger::load();
+    let from = env::predecessor_account_id();
+    validate_memo(&memo)?;
+    ledger.state.move_balance(from, to, amount)?;
+    let index = ledger.record(Operation::Transfer { from: from, to: to, amount: amount, spender: None }, memo);
+    ledger.commit();
+    // Return transaction index
+    env::ret(index.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for allowing another account to spend the caller's $TIME.
+// Replaces any previous allowance for the same spender.
+pub fn approve(spender: account::Id, amount: u64, expires_at: Option<u64>, memo: Option<Vec<u8>>) -> Result<(), TimeFeedError> {
+    let mut ledger = Ledger::load();
+    let from = env::predecessor_account_id();
+    validate_memo(&memo)?;
+    if from == spender {
+        return Err(TimeFeedError::SelfApproval);
+    }
+    if expires_at.is_some_and(|expires_at| expires_at <= env::block_timestamp()) {
+        return Err(TimeFeedError::AllowanceExpired);
+    }
+    // Set allowance in state
+    ledger.state.allowances.insert((from, spender), Allowance { amount: amount, expires_at: expires_at });
+    let index = ledger.record(Operation::Approve { from: from, spender: spender, amount: amount, expires_at: expires_at }, memo);
+    ledger.commit();
+    // Return transaction index
+    env::ret(index.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for spending $TIME another account has approved the caller for
+pub fn transfer_from(from: account::Id, to: account::Id, amount: u64, memo: Option<Vec<u8>>) -> Result<(), TimeFeedError> {
+    let mut ledger = Ledger::load();
+    let spender = env::predecessor_account_id();
+    validate_memo(&memo)?;
+    // Check if the allowance covers the transfer
+    let allowance = match ledger.state.allowances.get(&(from, spender)) {
+        Some(allowance) => allowance.clone(),
+        None => return Err(TimeFeedError::InsufficientAllowance),
+    };
+    if allowance.expires_at.is_some_and(|expires_at| expires_at <= env::block_timestamp()) {
+        return Err(TimeFeedError::AllowanceExpired);
+    }
+    if allowance.amount < amount {
+        return Err(TimeFeedError::InsufficientAllowance);
+    }
+    ledger.state.move_balance(from, to, amount)?;
+    // Use up the allowance
+    if allowance.amount == amount {
+        ledger.state.allowances.remove(&(from, spender));
+    } else {
+        ledger.state.allowances.insert((from, spender), Allowance { amount: allowance.amount - amount, expires_at: allowance.expires_at });
+    }
+    let index = ledger.record(Operation::Transfer { from: from, to: to, amount: amount, spender: Some(spender) }, memo);
+    ledger.commit();
+    // Return transaction index
+    env::ret(index.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for moving the caller's $TIME from the chain's balance module onto the ledger.
+// Before this ledger, $TIME was held in the chain's balance module. The caller's
+// whole balance there is handed to the contract and the same amount is minted
+// here, so it can only be imported once. Returns the amount imported.
+pub fn import_legacy_balance() -> Result<(), TimeFeedError> {
+    let mut ledger = Ledger::load();
+    let account_id = env::predecessor_account_id();
+    let contract = env::predecessor_contract_id();
+    // The contract's own holdings back post stakes and are imported by the minter
+    let amount = if account_id == contract { 0 } else { env::legacy_balance_of(account_id) };
+    if amount == 0 {
+        return Err(TimeFeedError::NothingToImport);
+    }
+    let total_supply = ledger.state.total_supply.checked_add(amount).ok_or(TimeFeedError::BalanceOverflow)?;
+    let balance = ledger.state.balance(&account_id).checked_add(amount).ok_or(TimeFeedError::BalanceOverflow)?;
+    env::legacy_transfer(account_id, contract, amount).map_err(|_| TimeFeedError::InsufficientBalance)?;
+    // Mint the imported $TIME, keeping track of what the contract was handed
+    ledger.state.total_supply = total_supply;
+    ledger.state.balances.insert(account_id, balance);
+    ledger.state.legacy_imported = ledger.state.legacy_imported.saturating_add(amount);
+    ledger.record(Operation::Mint { to: account_id, amount: amount }, Some(b"legacy_import".to_vec()));
+    ledger.commit();
+    // Return amount imported
+    env::ret(amount.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for moving the contract's own $TIME from the chain's balance module onto the ledger.
+// The contract's balance there backs the stakes of posts created before the
+// ledger, so it has to be imported before those posts pay anything out. Balances
+// users have imported since were handed to the contract too and are left out.
+// Only the minter can import it, and only once. Returns the amount imported.
+pub fn import_contract_balance() -> Result<(), TimeFeedError> {
+    let mut ledger = Ledger::load();
+    // Check if caller is the minter
+    if ledger.state.minter != Some(env::predecessor_account_id()) {
+        return Err(TimeFeedError::NotMinter);
+    }
+    if ledger.state.contract_balance_imported {
+        return Err(TimeFeedError::AlreadyImported);
+    }
+    let contract = env::predecessor_contract_id();
+    let amount = env::legacy_balance_of(contract).saturating_sub(ledger.state.legacy_imported);
+    let total_supply = ledger.state.total_supply.checked_add(amount).ok_or(TimeFeedError::BalanceOverflow)?;
+    let balance = ledger.state.balance(&contract).checked_add(amount).ok_or(TimeFeedError::BalanceOverflow)?;
+    // Mint the imported $TIME to the contract
+    ledger.state.total_supply = total_supply;
+    ledger.state.balances.insert(contract, balance);
+    ledger.state.contract_balance_imported = true;
+    ledger.record(Operation::Mint { to: contract, amount: amount }, Some(b"legacy_import".to_vec()));
+    ledger.commit();
+    // Return amount imported
+    env::ret(amount.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting the $TIME balance of an account
+pub fn get_balance_of(account_id: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Return balance
+    env::ret(state.balance(&account_id).to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting the total $TIME supply
+pub fn get_total_supply() -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Return total supply
+    env::ret(state.total_supply.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting what a spender may still spend of an owner's $TIME
+pub fn get_allowance(owner: account::Id, spender: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Expired allowances count as nothing
+    let now = env::block_timestamp();
+    let allowance = state.allowances.get(&(owner, spender))
+        .filter(|allowance| allowance.expires_at.is_none_or(|expires_at| expires_at > now))
+        .cloned()
+        .unwrap_or(Allowance { amount: 0, expires_at: None });
+    // Return allowance
+    env::ret(allowance.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting a page of the transaction log, oldest first
+pub fn get_transactions(start: u64, limit: u32) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(TimeFeedError::InvalidPageSize);
+    }
+    // Read the transactions in the page from their keys, with their index in the log
+    let end = start.saturating_add(limit as u64).min(state.transaction_count);
+    let transactions: Vec<(u64, Transaction)> = (start..end)
+        .map(|index| (index, env::read(&transaction_key(index)).expect("Missing transaction")))
+        .collect();
+    // Return page of transactions
+    env::ret(transactions.to_bytes().unwrap());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Host::{CallError, InMemoryHost};
+    use crate::Host::testing::{account, decode, new_host, CONTRACT};
+
+    // Install a host where account 1 holds the given amount of $TIME
+    fn funded(amount: u64) -> InMemoryHost {
+        let host = new_host();
+        host.set_caller(account(CONTRACT));
+        host.call(|| mint(account(1), amount, None)).unwrap();
+        host
+    }
+
+    #[test]
+    fn allowances_are_spent_in_part_and_expire() {
+        let host = funded(10);
+        host.set_caller(account(1));
+        host.call(|| approve(account(2), 6, Some(100), None)).unwrap();
+        // The spender can use the allowance over several transfers
+        host.set_caller(account(2));
+        host.call(|| transfer_from(account(1), account(3), 4, None)).unwrap();
+        let allowance: Allowance = decode(host.call(|| get_allowance(account(1), account(2))).unwrap());
+        assert_eq!(allowance.amount, 2);
+        assert_eq!(host.call(|| transfer_from(account(1), account(3), 3, None)), Err(CallError::Failed(TimeFeedError::InsufficientAllowance)));
+        // What is left cannot be spent once it has expired
+        host.set_block_timestamp(100);
+        assert_eq!(host.call(|| transfer_from(account(1), account(3), 2, None)), Err(CallError::Failed(TimeFeedError::AllowanceExpired)));
+        let allowance: Allowance = decode(host.call(|| get_allowance(account(1), account(2))).unwrap());
+        assert_eq!(allowance.amount, 0);
+        assert_eq!((balance_of(account(1)), balance_of(account(3))), (6, 4));
+    }
+
+    #[test]
+    fn burning_lowers_the_total_supply() {
+        let host = funded(10);
+        host.set_caller(account(1));
+        host.call(|| burn(3, None)).unwrap();
+        let total_supply: u64 = decode(host.call(get_total_supply).unwrap());
+        assert_eq!((total_supply, balance_of(account(1))), (7, 7));
+        assert_eq!(host.call(|| burn(8, None)), Err(CallError::Failed(TimeFeedError::InsufficientBalance)));
+    }
+
+    #[test]
+    fn bad_approvals_and_memos_are_rejected() {
+        let host = funded(10);
+        host.set_caller(account(1));
+        assert_eq!(host.call(|| approve(account(1), 5, None, None)), Err(CallError::Failed(TimeFeedError::SelfApproval)));
+        let memo = Some(vec![0; MAX_MEMO_LEN + 1]);
+        assert_eq!(host.call(|| transfer(account(2), 1, memo.clone())), Err(CallError::Failed(TimeFeedError::MemoTooLong)));
+        assert_eq!(host.call(|| burn(1, memo.clone())), Err(CallError::Failed(TimeFeedError::MemoTooLong)));
+        host.call(|| transfer(account(2), 1, Some(vec![0; MAX_MEMO_LEN]))).unwrap();
+    }
+
+    #[test]
+    fn transactions_are_stored_apart_from_the_state() {
+        let host = funded(100);
+        host.set_caller(account(1));
+        host.call(|| transfer(account(2), 1, None)).unwrap();
+        let state_size = host.stored_state()[State::STATE_KEY].len();
+        for _ in 0..19 {
+            host.call(|| transfer(account(2), 1, Some(b"tip".to_vec()))).unwrap();
+        }
+        // The log grows one key per transaction while the state stays the same size
+        assert_eq!(host.stored_state()[State::STATE_KEY].len(), state_size);
+        assert!(host.stored_state().contains_key(&transaction_key(20)));
+        let page: Vec<(u64, Transaction)> = decode(host.call(|| get_transactions(18, 10)).unwrap());
+        assert_eq!(page.iter().map(|(index, _)| *index).collect::<Vec<u64>>(), vec![18, 19, 20]);
+        assert_eq!(page[2].1.memo, Some(b"tip".to_vec()));
+    }
+
+    #[test]
+    fn legacy_balances_are_imported_once() {
+        let host = new_host();
+        host.set_legacy_balance(account(1), 7);
+        host.set_legacy_balance(account(CONTRACT), 30);
+        host.set_caller(account(1));
+        let imported: u64 = decode(host.call(import_legacy_balance).unwrap());
+        assert_eq!((imported, balance_of(account(1))), (7, 7));
+        assert_eq!(host.call(import_legacy_balance), Err(CallError::Failed(TimeFeedError::NothingToImport)));
+        // The contract keeps what backed its stakes, not what users have handed it since
+        assert_eq!(host.call(import_contract_balance), Err(CallError::Failed(TimeFeedError::NotMinter)));
+        host.set_caller(account(CONTRACT));
+        let imported: u64 = decode(host.call(import_contract_balance).unwrap());
+        assert_eq!((imported, balance_of(account(CONTRACT))), (30, 30));
+        assert_eq!(host.call(import_contract_balance), Err(CallError::Failed(TimeFeedError::AlreadyImported)));
+        let total_supply: u64 = decode(host.call(get_total_supply).unwrap());
+        assert_eq!(total_supply, 37);
+    }
+}
@@ -1,115 +1,1184 @@
 // Import necessary libraries
-use std::collections::HashMap;
-use icp_contracts::{account, env, sdk};
-use icp_contracts::hash::H256;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Bound;
+use icp_contracts::account;
+use icp_contracts::hash::{sha256, H256};
 use icp_contracts::bytesrepr::{ToBytes, FromBytes};
+use argon2::{Algorithm, Argon2, Params, Version};
+use subtle::ConstantTimeEq;
+use crate::Host::{env, ContractState};
+use crate::TokenManagement;
+use crate::Error::TimeFeedError;
+
+// Argon2id parameters by version: (version, memory in KiB, iterations, lanes).
+// Versions are never changed once released; stronger settings get a new version
+// and existing credentials are upgraded the next time their owner logs in.
+const KDF_PARAMS: [(u8, u32, u32, u32); 1] = [(1, 19 * 1024, 2, 1)];
+const CURRENT_KDF_VERSION: u8 = 1;
+
+// Longest password accepted, in bytes
+const MAX_PASSWORD_LEN: usize = 1024;
+
+// Longest email address accepted, in bytes
+const MAX_EMAIL_LEN: usize = 254;
+
+// How long a session stays valid after login, in seconds
+const SESSION_TTL: u64 = 30 * 24 * 60 * 60;
+
+// Domain tags for values derived from the random seed. Salts and session tokens
+// can be drawn from the same seed in one call, so each gets its own tag.
+const SALT_DOMAIN: &[u8] = b"time_feed/salt";
+const SESSION_DOMAIN: &[u8] = b"time_feed/session";
+
+// Longest device label accepted, in bytes
+const MAX_DEVICE_LEN: usize = 64;
+
+// Profile field limits, in characters
+const MAX_NAME_LEN: usize = 50;
+const MAX_BIO_LEN: usize = 280;
+const MAX_LOCATION_LEN: usize = 64;
+const MAX_LINK_LEN: usize = 200;
+
+// Most website links a profile can list
+const MAX_LINKS: usize = 5;
+
+// Maximum number of accounts returned in a single page
+const MAX_PAGE_SIZE: u32 = 100;
+
+// Key the first version of the contract stored its users under
+const LEGACY_STATE_KEY: &str = "state";
+
+// Define enum for a stored password credential.
+// Accounts migrated from the first version of the contract were registered with
+// a 32-byte hash computed by the client, never the plaintext. Their password is
+// those 32 bytes: clients must keep sending the same hash for them, even after
+// login has upgraded the credential to Argon2id, until the user sets a new
+// password through `update_user`. Every other account sends the plaintext.
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+enum Credential {
+    // Client-supplied hash stored as-is by the first version of the contract
+    Legacy(H256),
+    // Password hashed with Argon2id under the given parameter version
+    Argon2id { version: u8, salt: H256, hash: H256 },
+}
 
 // Define struct for user data
-#[derive(Debug, ToBytes, FromBytes)]
+#[derive(Debug, Clone, ToBytes, FromBytes)]
 struct User {
     name: String,
+    handle: String,
     email: String,
-    password: H256,
-    tokens: u64,
+    credential: Credential,
+    bio: String,
+    avatar: Option<H256>,
+    links: Vec<String>,
+    location: String,
+    joined_at: u64,
+    display: DisplaySettings,
 }
 
-// Define struct for contract state
+// Define struct for which optional profile fields other users can see
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+pub struct DisplaySettings {
+    pub show_location: bool,
+    pub show_join_date: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            show_location: true,
+            show_join_date: true,
+        }
+    }
+}
+
+// Define struct for a partial profile update.
+// Fields left as `None` are not changed; `avatar: Some(None)` removes the avatar.
 #[derive(Debug, ToBytes, FromBytes)]
+pub struct ProfileUpdate {
+    pub name: Option<String>,
+    pub bio: Option<String>,
+    pub avatar: Option<Option<H256>>,
+    pub links: Option<Vec<String>>,
+    pub location: Option<String>,
+    pub display: Option<DisplaySettings>,
+}
+
+// Define struct for contract state
+#[derive(Debug, Default, ToBytes, FromBytes)]
 struct State {
     users: HashMap<account::Id, User>,
+    accounts_by_email: HashMap<String, account::Id>,
+    accounts_by_handle: HashMap<String, account::Id>,
+    sessions: HashMap<H256, Session>,
+    sessions_by_account: HashMap<account::Id, BTreeSet<H256>>,
+    session_nonces: HashMap<account::Id, u64>,
+    following: HashMap<account::Id, BTreeSet<account::Id>>,
+    followers: HashMap<account::Id, BTreeSet<account::Id>>,
+    blocked: HashMap<account::Id, BTreeSet<account::Id>>,
+}
+
+// Define struct for a page of accounts, ordered by account id
+#[derive(Debug, ToBytes, FromBytes)]
+struct AccountPage {
+    accounts: Vec<account::Id>,
+    total: u64,
+    next_cursor: Option<account::Id>,
+}
+
+// Define struct for how many followers an account has and how many it follows
+#[derive(Debug, ToBytes, FromBytes)]
+struct FollowCounts {
+    followers: u64,
+    following: u64,
 }
 
-// Define function for registering a new user
-fn register_user(name: String, email: String, password: H256) {
-    let state = env::state();
-    // Check if email is already in use
-    if state.users.contains_key(&env::predecessor_account_id()) {
-        panic!("Email is already in use");
+// Define struct for what anyone can see about a user
+#[derive(Debug, ToBytes, FromBytes)]
+struct PublicProfile {
+    handle: String,
+    name: String,
+    bio: String,
+    avatar: Option<H256>,
+    links: Vec<String>,
+    location: Option<String>,
+    joined_at: Option<u64>,
+}
+
+// Define struct for what a user can see about themselves.
+// Never includes the password credential. `tokens` is read from the $TIME
+// ledger, which is the only record of balances.
+#[derive(Debug, ToBytes, FromBytes)]
+struct PrivateProfile {
+    handle: String,
+    name: String,
+    email: String,
+    tokens: u64,
+    bio: String,
+    avatar: Option<H256>,
+    links: Vec<String>,
+    location: String,
+    joined_at: u64,
+    display: DisplaySettings,
+}
+
+impl User {
+    // Get the view of the user shown to other accounts
+    fn public_profile(&self) -> PublicProfile {
+        PublicProfile {
+            handle: self.handle.clone(),
+            name: self.name.clone(),
+            bio: self.bio.clone(),
+            avatar: self.avatar,
+            links: self.links.clone(),
+            location: if self.display.show_location { Some(self.location.clone()) } else { None },
+            joined_at: if self.display.show_join_date { Some(self.joined_at) } else { None },
+        }
+    }
+
+    // Get the view of the user shown to the user themselves
+    fn private_profile(&self, account_id: &account::Id) -> PrivateProfile {
+        PrivateProfile {
+            handle: self.handle.clone(),
+            name: self.name.clone(),
+            email: self.email.clone(),
+            tokens: TokenManagement::balance_of(*account_id),
+            bio: self.bio.clone(),
+            avatar: self.avatar,
+            links: self.links.clone(),
+            location: self.location.clone(),
+            joined_at: self.joined_at,
+            display: self.display.clone(),
+        }
+    }
+}
+
+// Define struct for a login session.
+// Sessions are stored under the hash of their token, so the token itself is
+// only ever known to whoever logged in.
+#[derive(Debug, Clone, ToBytes, FromBytes)]
+struct Session {
+    account_id: account::Id,
+    device: String,
+    created_at: u64,
+    expires_at: u64,
+}
+
+// Define struct for the result of a successful login
+#[derive(Debug, ToBytes, FromBytes)]
+struct LoginResult {
+    account_id: account::Id,
+    token: H256,
+    expires_at: u64,
+    user: PrivateProfile,
+}
+
+impl State {
+    // Record that one account follows another
+    fn add_follow(&mut self, follower: account::Id, followee: account::Id) {
+        self.following.entry(follower).or_default().insert(followee);
+        self.followers.entry(followee).or_default().insert(follower);
+    }
+
+    // Remove a follow, returning whether there was one
+    fn remove_follow(&mut self, follower: &account::Id, followee: &account::Id) -> bool {
+        let removed = remove_from_set(&mut self.following, follower, followee);
+        remove_from_set(&mut self.followers, followee, follower);
+        removed
+    }
+
+    // Check if either account has blocked the other
+    fn is_blocked_between(&self, a: &account::Id, b: &account::Id) -> bool {
+        self.blocked.get(a).is_some_and(|blocked| blocked.contains(b))
+            || self.blocked.get(b).is_some_and(|blocked| blocked.contains(a))
+    }
+
+    // Drop an account from the follow graph, in both directions
+    fn remove_from_graph(&mut self, account_id: &account::Id) {
+        for followee in self.following.remove(account_id).unwrap_or_default() {
+            remove_from_set(&mut self.followers, &followee, account_id);
+        }
+        for follower in self.followers.remove(account_id).unwrap_or_default() {
+            remove_from_set(&mut self.following, &follower, account_id);
+        }
+        self.blocked.remove(account_id);
+    }
+
+    // Drop a session from state
+    fn revoke_session(&mut self, session_key: &H256) {
+        if let Some(session) = self.sessions.remove(session_key) {
+            if let Some(keys) = self.sessions_by_account.get_mut(&session.account_id) {
+                keys.remove(session_key);
+                if keys.is_empty() {
+                    self.sessions_by_account.remove(&session.account_id);
+                }
+            }
+        }
+    }
+
+    // Drop every session of an account
+    fn revoke_all_sessions(&mut self, account_id: &account::Id) {
+        if let Some(keys) = self.sessions_by_account.remove(account_id) {
+            for key in keys {
+                self.sessions.remove(&key);
+            }
+        }
     }
+
+    // Derive a fresh session token for an account.
+    // Mixing in the account and a per-account counter keeps tokens distinct even
+    // if the host hands out the same seed more than once.
+    fn new_session_token(&mut self, account_id: &account::Id) -> H256 {
+        let nonce = self.session_nonces.entry(*account_id).or_insert(0);
+        *nonce += 1;
+        let mut seed = SESSION_DOMAIN.to_vec();
+        seed.extend(env::random_seed().to_bytes().unwrap());
+        seed.extend(account_id.to_bytes().unwrap());
+        seed.extend(nonce.to_bytes().unwrap());
+        sha256(&seed)
+    }
+
+    // Drop the expired sessions of an account
+    fn prune_sessions(&mut self, account_id: &account::Id, now: u64) {
+        let expired: Vec<H256> = match self.sessions_by_account.get(account_id) {
+            Some(keys) => keys.iter().filter(|key| self.sessions[*key].expires_at <= now).cloned().collect(),
+            None => return,
+        };
+        for key in expired {
+            self.revoke_session(&key);
+        }
+    }
+}
+
+// Remove a value from a set in a map, dropping the entry once it is empty
+fn remove_from_set(map: &mut HashMap<account::Id, BTreeSet<account::Id>>, key: &account::Id, value: &account::Id) -> bool {
+    let removed = match map.get_mut(key) {
+        Some(set) => set.remove(value),
+        None => false,
+    };
+    if map.get(key).is_some_and(|set| set.is_empty()) {
+        map.remove(key);
+    }
+    removed
+}
+
+// Build a page of accounts from a set, starting after the cursor
+fn account_page(accounts: Option<&BTreeSet<account::Id>>, limit: u32, cursor: Option<account::Id>) -> Result<AccountPage, TimeFeedError> {
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(TimeFeedError::InvalidPageSize);
+    }
+    let accounts = match accounts {
+        Some(accounts) => accounts,
+        None => return Ok(AccountPage { accounts: Vec::new(), total: 0, next_cursor: None }),
+    };
+    let start = match cursor {
+        Some(cursor) => Bound::Excluded(cursor),
+        None => Bound::Unbounded,
+    };
+    let mut page: Vec<account::Id> = accounts.range((start, Bound::Unbounded)).take(limit as usize + 1).cloned().collect();
+    // Only hand out a cursor if there is something after this page
+    let next_cursor = if page.len() > limit as usize {
+        page.truncate(limit as usize);
+        page.last().cloned()
+    } else {
+        None
+    };
+    Ok(AccountPage { accounts: page, total: accounts.len() as u64, next_cursor: next_cursor })
+}
+
+// Get the accounts an account follows, for building its home feed
+pub(crate) fn following_of(account_id: &account::Id) -> BTreeSet<account::Id> {
+    let state: State = env::state();
+    state.following.get(account_id).cloned().unwrap_or_default()
+}
+
+// Get the accounts blocked either way with an account, whose posts its feed should skip
+pub(crate) fn blocked_between(account_id: &account::Id) -> BTreeSet<account::Id> {
+    let state: State = env::state();
+    let mut accounts = state.blocked.get(account_id).cloned().unwrap_or_default();
+    accounts.extend(state.blocked.keys().filter(|other| state.is_blocked_between(account_id, other)).cloned());
+    accounts
+}
+
+// Resolve the account an entry point acts for.
+// Without a session this is the caller itself; with one, it is the account that
+// logged in to create the session, which lets off-chain relayers act for users.
+pub(crate) fn authenticate(session: Option<H256>) -> Result<account::Id, TimeFeedError> {
+    let token = match session {
+        Some(token) => token,
+        None => return Ok(env::predecessor_account_id()),
+    };
+    let state: State = env::state();
+    let session = match state.sessions.get(&sha256(token.as_bytes())) {
+        Some(session) => session,
+        None => return Err(TimeFeedError::InvalidSession),
+    };
+    if session.expires_at <= env::block_timestamp() {
+        return Err(TimeFeedError::SessionExpired);
+    }
+    Ok(session.account_id)
+}
+
+// Check a display name is non-empty and within the length limit
+fn validate_name(name: &str) -> Result<(), TimeFeedError> {
+    let length = name.trim().chars().count();
+    if length == 0 || length > MAX_NAME_LEN {
+        return Err(TimeFeedError::InvalidName);
+    }
+    Ok(())
+}
+
+// Check a bio is within the length limit
+fn validate_bio(bio: &str) -> Result<(), TimeFeedError> {
+    if bio.chars().count() > MAX_BIO_LEN {
+        return Err(TimeFeedError::InvalidBio);
+    }
+    Ok(())
+}
+
+// Check a location is within the length limit
+fn validate_location(location: &str) -> Result<(), TimeFeedError> {
+    if location.chars().count() > MAX_LOCATION_LEN {
+        return Err(TimeFeedError::InvalidLocation);
+    }
+    Ok(())
+}
+
+// Check website links are http(s) URLs within the count and length limits
+fn validate_links(links: &[String]) -> Result<(), TimeFeedError> {
+    if links.len() > MAX_LINKS {
+        return Err(TimeFeedError::InvalidLink);
+    }
+    for link in links {
+        let has_scheme = link.starts_with("https://") || link.starts_with("http://");
+        if !has_scheme || link.chars().count() > MAX_LINK_LEN || link.chars().any(char::is_whitespace) {
+            return Err(TimeFeedError::InvalidLink);
+        }
+    }
+    Ok(())
+}
+
+// Normalize an email address for comparison and check it looks like one
+fn normalize_email(email: &str) -> Result<String, TimeFeedError> {
+    let email = email.trim().to_lowercase();
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => !local.is_empty() && domain.contains('.') && !domain.contains('@'),
+        None => false,
+    };
+    if !valid || email.len() > MAX_EMAIL_LEN {
+        return Err(TimeFeedError::InvalidEmail);
+    }
+    Ok(email)
+}
+
+// Normalize a handle for comparison and check it is 3 to 30 letters, digits or underscores
+fn normalize_handle(handle: &str) -> Result<String, TimeFeedError> {
+    let handle = handle.trim().trim_start_matches('@').to_lowercase();
+    if handle.len() < 3 || handle.len() > 30 {
+        return Err(TimeFeedError::InvalidHandle);
+    }
+    if !handle.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+        return Err(TimeFeedError::InvalidHandle);
+    }
+    Ok(handle)
+}
+
+// Check that an email and handle are free, or already belong to the given account
+fn check_unique(state: &State, account_id: &account::Id, email: &str, handle: &str) -> Result<(), TimeFeedError> {
+    if state.accounts_by_email.get(email).is_some_and(|owner| owner != account_id) {
+        return Err(TimeFeedError::EmailTaken);
+    }
+    if state.accounts_by_handle.get(handle).is_some_and(|owner| owner != account_id) {
+        return Err(TimeFeedError::HandleTaken);
+    }
+    Ok(())
+}
+
+impl ContractState for State {
+    const STATE_KEY: &'static str = "time_feed/users";
+}
+
+// Define struct for user data as stored by the first version of the contract
+#[derive(Debug, ToBytes, FromBytes)]
+struct LegacyUser {
+    name: String,
+    email: String,
+    password: H256,
+    tokens: u64,
+}
+
+// Define struct for contract state as stored by the first version of the contract
+#[derive(Debug, ToBytes, FromBytes)]
+struct LegacyState {
+    users: HashMap<account::Id, LegacyUser>,
+}
+
+// Generate a handle for a migrated account, which had none.
+// Uses `hex_len` hex digits of a hash of the account id.
+fn legacy_handle(account_id: &account::Id, hex_len: usize) -> String {
+    let digest = sha256(&account_id.to_bytes().unwrap());
+    let hex: String = digest.as_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("user_{}", &hex[..hex_len])
+}
+
+// Add users stored by the first version of the contract to the current state.
+// Passwords become `Credential::Legacy`. `tokens` is dropped: the first version
+// never credited it, and $TIME its users hold in the chain's balance module is
+// moved to the ledger with `TokenManagement::import_legacy_balance`. Accounts
+// that have registered again since the upgrade keep their new record. Emails
+// that are invalid or already taken are dropped, so those users have to set one
+// through `update_user` before they can log in. Returns the number of users added.
+fn migrate_legacy_state(state: &mut State, legacy: LegacyState) -> u64 {
+    let mut migrated = 0;
+    // Go through accounts in order so the migration is deterministic
+    let mut users: Vec<(account::Id, LegacyUser)> = legacy.users.into_iter().collect();
+    users.sort_by_key(|a| a.0);
+    for (account_id, legacy_user) in users {
+        if state.users.contains_key(&account_id) {
+            continue;
+        }
+        let email = match normalize_email(&legacy_user.email) {
+            Ok(email) if !state.accounts_by_email.contains_key(&email) => email,
+            _ => String::new(),
+        };
+        // Fall back to a longer handle if someone has registered the short one
+        let mut handle = legacy_handle(&account_id, 16);
+        if state.accounts_by_handle.contains_key(&handle) {
+            handle = legacy_handle(&account_id, 25);
+        }
+        if !email.is_empty() {
+            state.accounts_by_email.insert(email.clone(), account_id);
+        }
+        state.accounts_by_handle.insert(handle.clone(), account_id);
+        state.users.insert(account_id, User {
+            name: legacy_user.name,
+            handle: handle,
+            email: email,
+            credential: Credential::Legacy(legacy_user.password),
+            bio: String::new(),
+            avatar: None,
+            links: Vec::new(),
+            location: String::new(),
+            joined_at: env::block_timestamp(),
+            display: DisplaySettings::default(),
+        });
+        migrated += 1;
+    }
+    migrated
+}
+
+// Define function for migrating the users of the first version of the contract.
+// Run once by the contract account after upgrading from the first version. The
+// old state is deleted once its users have been added, so it cannot be migrated
+// twice. Returns the number of users migrated.
+pub fn migrate_legacy_users() -> Result<(), TimeFeedError> {
+    // Check if caller is the contract account
+    if env::predecessor_account_id() != env::predecessor_contract_id() {
+        return Err(TimeFeedError::NotAdmin);
+    }
+    let legacy = match env::legacy_state::<LegacyState>(LEGACY_STATE_KEY) {
+        Some(legacy) => legacy,
+        None => return Err(TimeFeedError::NothingToMigrate),
+    };
+    let mut state: State = env::state();
+    let migrated = migrate_legacy_state(&mut state, legacy);
+    env::commit_state(state);
+    env::remove(LEGACY_STATE_KEY);
+    // Return number of users migrated
+    env::ret(migrated.to_bytes().unwrap());
+    Ok(())
+}
+
+// Hash a password with the Argon2id parameters of the given version
+fn hash_password(password: &[u8], salt: &H256, version: u8) -> Option<H256> {
+    let (_, memory, iterations, lanes) = KDF_PARAMS.iter().find(|params| params.0 == version)?;
+    let params = Params::new(*memory, *iterations, *lanes, Some(32)).ok()?;
+    let mut hash = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password, salt.as_bytes(), &mut hash)
+        .ok()?;
+    Some(H256::from(hash))
+}
+
+// Create a credential for a password with a fresh salt and the current parameters
+fn new_credential(account_id: &account::Id, password: &[u8]) -> Result<Credential, TimeFeedError> {
+    if password.is_empty() || password.len() > MAX_PASSWORD_LEN {
+        return Err(TimeFeedError::InvalidPassword);
+    }
+    let mut seed = SALT_DOMAIN.to_vec();
+    seed.extend(env::random_seed().to_bytes().unwrap());
+    seed.extend(account_id.to_bytes().unwrap());
+    let salt = sha256(&seed);
+    let hash = hash_password(password, &salt, CURRENT_KDF_VERSION).ok_or(TimeFeedError::InvalidPassword)?;
+    Ok(Credential::Argon2id { version: CURRENT_KDF_VERSION, salt: salt, hash: hash })
+}
+
+// Check a password against a stored credential in constant time
+fn verify_password(credential: &Credential, password: &[u8]) -> bool {
+    match credential {
+        Credential::Legacy(hash) => hash.as_bytes().ct_eq(password).into(),
+        Credential::Argon2id { version, salt, hash } => match hash_password(password, salt, *version) {
+            Some(computed) => computed.as_bytes().ct_eq(hash.as_bytes()).into(),
+            None => false,
+        },
+    }
+}
+
+// Check if a credential should be rehashed with the current parameters
+fn needs_rehash(credential: &Credential) -> bool {
+    match credential {
+        Credential::Legacy(_) => true,
+        Credential::Argon2id { version, .. } => *version != CURRENT_KDF_VERSION,
+    }
+}
+
+// Define function for registering a new user.
+// Emails and handles are compared case-insensitively and must be unique.
+pub fn register_user(name: String, handle: String, email: String, password: Vec<u8>) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    let account_id = env::predecessor_account_id();
+    // Check if account is already registered
+    if state.users.contains_key(&account_id) {
+        return Err(TimeFeedError::AlreadyRegistered);
+    }
+    validate_name(&name)?;
+    // Check if email and handle are already in use
+    let email = normalize_email(&email)?;
+    let handle = normalize_handle(&handle)?;
+    check_unique(&state, &account_id, &email, &handle)?;
     // Create new user
     let new_user = User {
         name: name,
-        email: email,
-        password: password,
-        tokens: 0
+        handle: handle.clone(),
+        email: email.clone(),
+        credential: new_credential(&account_id, &password)?,
+        bio: String::new(),
+        avatar: None,
+        links: Vec::new(),
+        location: String::new(),
+        joined_at: env::block_timestamp(),
+        display: DisplaySettings::default(),
     };
     // Add new user to state
-    state.users.insert(env::predecessor_account_id(), new_user);
+    state.users.insert(account_id, new_user);
+    state.accounts_by_email.insert(email, account_id);
+    state.accounts_by_handle.insert(handle, account_id);
     env::commit_state(state);
+    Ok(())
 }
 
-// Define function for user login
-fn login(email: String, password: H256) {
-    let state = env::state();
-    // Check if email exists in state
-    if !state.users.contains_key(&env::predecessor_account_id()) {
-        panic!("Invalid email or password");
+// Define function for user login.
+// Starts a new session labelled with the given device and returns its token,
+// which can be passed to entry points instead of calling as the account.
+pub fn login(email: String, password: Vec<u8>, device: String) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Check if device label is valid
+    if device.len() > MAX_DEVICE_LEN {
+        return Err(TimeFeedError::InvalidDevice);
     }
+    // Find the account registered with the email
+    let account_id = match normalize_email(&email).ok().and_then(|email| state.accounts_by_email.get(&email).cloned()) {
+        Some(account_id) => account_id,
+        None => return Err(TimeFeedError::InvalidCredentials),
+    };
     // Get user from state
-    let user = state.users.get(&env::predecessor_account_id()).unwrap();
+    let user = state.users.get(&account_id).unwrap();
     // Check if password matches
-    if user.password != password {
-        panic!("Invalid email or password");
+    if !verify_password(&user.credential, &password) {
+        return Err(TimeFeedError::InvalidCredentials);
+    }
+    // Upgrade legacy and outdated credentials now that we have the password
+    if needs_rehash(&user.credential) {
+        let credential = new_credential(&account_id, &password)?;
+        state.users.get_mut(&account_id).unwrap().credential = credential;
     }
-    // Return user data
-    env::ret(user.to_bytes().unwrap());
+    // Start a new session, clearing out any that have expired
+    let now = env::block_timestamp();
+    state.prune_sessions(&account_id, now);
+    let token = state.new_session_token(&account_id);
+    let session_key = sha256(token.as_bytes());
+    let session = Session {
+        account_id: account_id,
+        device: device,
+        created_at: now,
+        expires_at: now + SESSION_TTL,
+    };
+    state.sessions.insert(session_key, session);
+    state.sessions_by_account.entry(account_id).or_default().insert(session_key);
+    let result = LoginResult {
+        account_id: account_id,
+        token: token,
+        expires_at: now + SESSION_TTL,
+        user: state.users.get(&account_id).unwrap().private_profile(&account_id),
+    };
+    env::commit_state(state);
+    // Return session and user data
+    env::ret(result.to_bytes().unwrap());
+    Ok(())
 }
 
-// Define function for updating user data
-fn update_user(name: String, email: String, password: H256) {
-    let state = env::state();
-    // Check if email exists in state
-    if !state.users.contains_key(&env::predecessor_account_id()) {
-        panic!("User not found");
+// Define function for updating user data.
+// Changing the password ends every session of the account, so a leaked
+// password stops working everywhere once it is replaced.
+pub fn update_user(name: String, handle: String, email: String, password: Vec<u8>) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    let account_id = env::predecessor_account_id();
+    // Check if user exists in state
+    if !state.users.contains_key(&account_id) {
+        return Err(TimeFeedError::UserNotFound);
     }
+    validate_name(&name)?;
+    // Check if the new email and handle are free
+    let email = normalize_email(&email)?;
+    let handle = normalize_handle(&handle)?;
+    check_unique(&state, &account_id, &email, &handle)?;
+    let credential = new_credential(&account_id, &password)?;
+    let password_changed = !verify_password(&state.users[&account_id].credential, &password);
     // Get user from state
-    let mut user = state.users.get_mut(&env::predecessor_account_id()).unwrap();
+    let user = state.users.get_mut(&account_id).unwrap();
+    let old_email = std::mem::replace(&mut user.email, email.clone());
+    let old_handle = std::mem::replace(&mut user.handle, handle.clone());
     // Update user data
     user.name = name;
-    user.email = email;
-    user.password = password;
+    user.credential = credential;
+    // Move the email and handle indexes over to the new values
+    state.accounts_by_email.remove(&old_email);
+    state.accounts_by_handle.remove(&old_handle);
+    state.accounts_by_email.insert(email, account_id);
+    state.accounts_by_handle.insert(handle, account_id);
+    if password_changed {
+        state.revoke_all_sessions(&account_id);
+    }
     // Commit state
     env::commit_state(state);
+    Ok(())
+}
+
+// Define function for updating profile fields.
+// Only the fields present in the update are changed, and no password is needed.
+pub fn update_profile(update: ProfileUpdate) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Check if user exists in state
+    let user = match state.users.get_mut(&env::predecessor_account_id()) {
+        Some(user) => user,
+        None => return Err(TimeFeedError::UserNotFound),
+    };
+    // Check every field before changing any of them
+    if let Some(name) = &update.name {
+        validate_name(name)?;
+    }
+    if let Some(bio) = &update.bio {
+        validate_bio(bio)?;
+    }
+    if let Some(links) = &update.links {
+        validate_links(links)?;
+    }
+    if let Some(location) = &update.location {
+        validate_location(location)?;
+    }
+    // Update user data
+    if let Some(name) = update.name {
+        user.name = name;
+    }
+    if let Some(bio) = update.bio {
+        user.bio = bio;
+    }
+    if let Some(avatar) = update.avatar {
+        user.avatar = avatar;
+    }
+    if let Some(links) = update.links {
+        user.links = links;
+    }
+    if let Some(location) = update.location {
+        user.location = location;
+    }
+    if let Some(display) = update.display {
+        user.display = display;
+    }
+    // Commit state
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for looking up an account by handle
+pub fn get_account_by_handle(handle: String) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Find the account registered with the handle
+    let account_id = match normalize_handle(&handle).ok().and_then(|handle| state.accounts_by_handle.get(&handle).cloned()) {
+        Some(account_id) => account_id,
+        None => return Err(TimeFeedError::UserNotFound),
+    };
+    // Return account id
+    env::ret(account_id.to_bytes().unwrap());
+    Ok(())
 }
 
 // Define function for getting user data
-fn get_user() {
-    let state = env::state();
+pub fn get_user() -> Result<(), TimeFeedError> {
+    let state: State = env::state();
     // Check if email exists in state
     if !state.users.contains_key(&env::predecessor_account_id()) {
-        panic!("User not found");
+        return Err(TimeFeedError::UserNotFound);
     }
     // Get user from state
     let user = state.users.get(&env::predecessor_account_id()).unwrap();
-    // Return user data
-    env::ret(user.to_bytes().unwrap());
+    // Return the user's own view of their data
+    env::ret(user.private_profile(&env::predecessor_account_id()).to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting the public profile of any user
+pub fn get_public_profile(account_id: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    // Check if user exists in state
+    let user = match state.users.get(&account_id) {
+        Some(user) => user,
+        None => return Err(TimeFeedError::UserNotFound),
+    };
+    // Return public profile
+    env::ret(user.public_profile().to_bytes().unwrap());
+    Ok(())
 }
 
 // Define function for deleting user
-fn delete_user() {
-    let state = env::state();
+pub fn delete_user() -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
     // Check if email exists in state
     if !state.users.contains_key(&env::predecessor_account_id()) {
-        panic!("User not found");
+        return Err(TimeFeedError::UserNotFound);
     }
-    // Remove user from state
-    state.users.remove(&env::predecessor_account_id());
+    // Remove user and their email and handle from state
+    let user = state.users.remove(&env::predecessor_account_id()).unwrap();
+    state.accounts_by_email.remove(&user.email);
+    state.accounts_by_handle.remove(&user.handle);
+    state.revoke_all_sessions(&env::predecessor_account_id());
+    // Remove user from the follow graph
+    state.remove_from_graph(&env::predecessor_account_id());
     // Commit state
     env::commit_state(state);
+    Ok(())
 }
 
-// Define function for user logout
-fn logout() {
-    let state = env::state();
-    // Check if email exists in state
-    if !state.users.contains_key(&env::predecessor_account_id()) {
-        panic!("User not found");
+// Define function for following another user
+pub fn follow(account_id: account::Id) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    let caller = env::predecessor_account_id();
+    // Check if both users exist in state
+    if !state.users.contains_key(&caller) || !state.users.contains_key(&account_id) {
+        return Err(TimeFeedError::UserNotFound);
     }
-    // Get user from state
-    let user = state.users.get(&env::predecessor_account_id()).unwrap();
-    // Return user data
-    env::ret(user.to_bytes().unwrap());
+    // Check if the follow is allowed
+    if caller == account_id {
+        return Err(TimeFeedError::CannotFollowSelf);
+    }
+    if state.is_blocked_between(&caller, &account_id) {
+        return Err(TimeFeedError::Blocked);
+    }
+    if state.following.get(&caller).is_some_and(|following| following.contains(&account_id)) {
+        return Err(TimeFeedError::AlreadyFollowing);
+    }
+    // Add follow to state
+    state.add_follow(caller, account_id);
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for unfollowing a user
+pub fn unfollow(account_id: account::Id) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Remove follow from state
+    if !state.remove_follow(&env::predecessor_account_id(), &account_id) {
+        return Err(TimeFeedError::NotFollowing);
+    }
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for blocking a user.
+// Removes any follow between the two accounts and stops either from following
+// the other until unblocked.
+pub fn block_user(account_id: account::Id) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    let caller = env::predecessor_account_id();
+    // Check if user exists in state
+    if !state.users.contains_key(&caller) {
+        return Err(TimeFeedError::UserNotFound);
+    }
+    if caller == account_id {
+        return Err(TimeFeedError::CannotFollowSelf);
+    }
+    // Add block and drop follows in both directions
+    state.blocked.entry(caller).or_default().insert(account_id);
+    state.remove_follow(&caller, &account_id);
+    state.remove_follow(&account_id, &caller);
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for unblocking a user
+pub fn unblock_user(account_id: account::Id) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    // Remove block from state
+    if !remove_from_set(&mut state.blocked, &env::predecessor_account_id(), &account_id) {
+        return Err(TimeFeedError::NotBlocked);
+    }
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for getting a page of an account's followers
+pub fn get_followers(account_id: account::Id, limit: u32, cursor: Option<account::Id>) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    let page = account_page(state.followers.get(&account_id), limit, cursor)?;
+    // Return page of followers
+    env::ret(page.to_bytes().unwrap());
+    Ok(())
 }
 
- 
+// Define function for getting a page of the accounts an account follows
+pub fn get_following(account_id: account::Id, limit: u32, cursor: Option<account::Id>) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    let page = account_page(state.following.get(&account_id), limit, cursor)?;
+    // Return page of followed accounts
+    env::ret(page.to_bytes().unwrap());
+    Ok(())
+}
 
+// Define function for getting follower and following counts of an account
+pub fn get_follow_counts(account_id: account::Id) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    let counts = FollowCounts {
+        followers: state.followers.get(&account_id).map_or(0, |followers| followers.len() as u64),
+        following: state.following.get(&account_id).map_or(0, |following| following.len() as u64),
+    };
+    // Return follow counts
+    env::ret(counts.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for getting a page of the accounts the caller has blocked
+pub fn get_blocked(limit: u32, cursor: Option<account::Id>) -> Result<(), TimeFeedError> {
+    let state: State = env::state();
+    let page = account_page(state.blocked.get(&env::predecessor_account_id()), limit, cursor)?;
+    // Return page of blocked accounts
+    env::ret(page.to_bytes().unwrap());
+    Ok(())
+}
+
+// Define function for user logout.
+// Ends the session with the given token.
+pub fn logout(token: H256) -> Result<(), TimeFeedError> {
+    let mut state: State = env::state();
+    let session_key = sha256(token.as_bytes());
+    // Check if session exists in state
+    if !state.sessions.contains_key(&session_key) {
+        return Err(TimeFeedError::InvalidSession);
+    }
+    // Remove session from state
+    state.revoke_session(&session_key);
+    env::commit_state(state);
+    Ok(())
+}
+
+// Define function for logging out everywhere.
+// Ends every session of the account, including the one used to call this.
+pub fn logout_all(session: Option<H256>) -> Result<(), TimeFeedError> {
+    let account_id = authenticate(session)?;
+    let mut state: State = env::state();
+    // Check if user exists in state
+    if !state.users.contains_key(&account_id) {
+        return Err(TimeFeedError::UserNotFound);
+    }
+    // Remove every session of the user from state
+    state.revoke_all_sessions(&account_id);
+    env::commit_state(state);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Host::{CallError, Host, InMemoryHost};
+    use crate::Host::testing::{account, decode, new_host, CONTRACT};
+
+    #[test]
+    fn legacy_users_are_migrated() {
+        let mut host = new_host();
+        let client_hash = sha256(b"correct horse");
+        let legacy_user = |name: &str, email: &str| LegacyUser {
+            name: String::from(name),
+            email: String::from(email),
+            password: client_hash,
+            tokens: 0,
+        };
+        let mut users = HashMap::new();
+        users.insert(account(1), legacy_user("Alice", "Alice@Example.com"));
+        users.insert(account(2), legacy_user("Bob", "bob@example.com"));
+        host.write_state(LEGACY_STATE_KEY, LegacyState { users: users }.to_bytes().unwrap());
+        // Bob registers again after the upgrade, before the migration runs
+        host.set_caller(account(2));
+        host.call(|| register_user(String::from("Robert"), String::from("bob"), String::from("robert@example.com"), b"battery staple".to_vec())).unwrap();
+        // Only the contract account can run the migration, and it only runs once
+        assert_eq!(host.call(migrate_legacy_users), Err(CallError::Failed(TimeFeedError::NotAdmin)));
+        host.set_caller(account(CONTRACT));
+        let migrated: u64 = decode(host.call(migrate_legacy_users).unwrap());
+        assert_eq!(migrated, 1);
+        assert!(host.read_state(LEGACY_STATE_KEY).is_none());
+        assert_eq!(host.call(migrate_legacy_users), Err(CallError::Failed(TimeFeedError::NothingToMigrate)));
+        let state: State = env::state();
+        assert_eq!(state.users[&account(2)].name, "Robert");
+        // Migrated users log in with the hash their client has always sent
+        host.set_caller(account(1));
+        let login_with = |password: &[u8]| login(String::from("alice@example.com"), password.to_vec(), String::from("phone"));
+        assert_eq!(host.call(|| login_with(b"correct horse")), Err(CallError::Failed(TimeFeedError::InvalidCredentials)));
+        let result: LoginResult = decode(host.call(|| login_with(client_hash.as_bytes())).unwrap());
+        assert_eq!(result.account_id, account(1));
+        assert_eq!(result.user.handle, legacy_handle(&account(1), 16));
+        // The same bytes keep working once the credential has been upgraded
+        host.call(|| login_with(client_hash.as_bytes())).unwrap();
+    }
+
+    #[test]
+    fn handles_and_emails_are_unique_whatever_the_case() {
+        let host = new_host();
+        host.set_caller(account(1));
+        host.call(|| register_user(String::from("Alice"), String::from("@Alice_1"), String::from("Alice@Example.com"), b"correct horse".to_vec())).unwrap();
+        // Another account cannot take either by changing the case
+        host.set_caller(account(2));
+        let register_with = |handle: &str, email: &str| register_user(String::from("Mallory"), String::from(handle), String::from(email), b"correct horse".to_vec());
+        assert_eq!(host.call(|| register_with("ALICE_1", "mallory@example.com")), Err(CallError::Failed(TimeFeedError::HandleTaken)));
+        assert_eq!(host.call(|| register_with("mallory", "alice@EXAMPLE.com")), Err(CallError::Failed(TimeFeedError::EmailTaken)));
+        assert_eq!(host.call(|| register_with("no", "mallory@example.com")), Err(CallError::Failed(TimeFeedError::InvalidHandle)));
+        // Handles are looked up the same way, with or without the @
+        let found: account::Id = decode(host.call(|| get_account_by_handle(String::from("alice_1"))).unwrap());
+        assert_eq!(found, account(1));
+        let found: account::Id = decode(host.call(|| get_account_by_handle(String::from("@ALICE_1"))).unwrap());
+        assert_eq!(found, account(1));
+        // A changed handle is freed and the new one is found instead
+        host.set_caller(account(1));
+        host.call(|| update_user(String::from("Alice"), String::from("alice"), String::from("alice@example.com"), b"correct horse".to_vec())).unwrap();
+        assert_eq!(host.call(|| get_account_by_handle(String::from("alice_1"))), Err(CallError::Failed(TimeFeedError::UserNotFound)));
+        let found: account::Id = decode(host.call(|| get_account_by_handle(String::from("Alice"))).unwrap());
+        assert_eq!(found, account(1));
+    }
+
+    #[test]
+    fn session_tokens_are_unique() {
+        let host = new_host();
+        host.set_caller(account(1));
+        host.call(|| register_user(String::from("Alice"), String::from("alice"), String::from("alice@example.com"), b"correct horse".to_vec())).unwrap();
+        // Every login hands out a new token, and each one opens its own session
+        let first: LoginResult = decode(host.call(|| login(String::from("alice@example.com"), b"correct horse".to_vec(), String::from("phone"))).unwrap());
+        let second: LoginResult = decode(host.call(|| login(String::from("alice@example.com"), b"correct horse".to_vec(), String::from("laptop"))).unwrap());
+        assert_ne!(first.token, second.token);
+        let state: State = env::state();
+        assert_eq!(state.sessions_by_account[&account(1)].len(), 2);
+        assert_eq!(authenticate(Some(first.token)), Ok(account(1)));
+        assert_eq!(authenticate(Some(second.token)), Ok(account(1)));
+    }
+
+    #[test]
+    fn changing_the_password_ends_every_session() {
+        let host = new_host();
+        host.set_caller(account(1));
+        host.call(|| register_user(String::from("Alice"), String::from("alice"), String::from("alice@example.com"), b"correct horse".to_vec())).unwrap();
+        let login_with = |password: &[u8]| login(String::from("alice@example.com"), password.to_vec(), String::from("phone"));
+        let session: LoginResult = decode(host.call(|| login_with(b"correct horse")).unwrap());
+        // Keeping the same password keeps the session open
+        host.call(|| update_user(String::from("Alice"), String::from("alice"), String::from("alice@example.com"), b"correct horse".to_vec())).unwrap();
+        assert_eq!(authenticate(Some(session.token)), Ok(account(1)));
+        // A new password ends it, and only the new password logs in
+        host.call(|| update_user(String::from("Alice"), String::from("alice"), String::from("alice@example.com"), b"battery staple".to_vec())).unwrap();
+        assert_eq!(authenticate(Some(session.token)), Err(TimeFeedError::InvalidSession));
+        assert_eq!(host.call(|| login_with(b"correct horse")), Err(CallError::Failed(TimeFeedError::InvalidCredentials)));
+        host.call(|| login_with(b"battery staple")).unwrap();
+    }
+
+    // Build a profile update that changes nothing
+    fn no_changes() -> ProfileUpdate {
+        ProfileUpdate { name: None, bio: None, avatar: None, links: None, location: None, display: None }
+    }
+
+    #[test]
+    fn public_profiles_show_only_what_the_user_allows() {
+        let host = new_host();
+        host.set_block_timestamp(1000);
+        host.set_caller(account(1));
+        host.call(|| register_user(String::from("Alice"), String::from("alice"), String::from("alice@example.com"), b"correct horse".to_vec())).unwrap();
+        host.call(|| update_profile(ProfileUpdate { location: Some(String::from("Lisbon")), ..no_changes() })).unwrap();
+        // Anyone can see the public fields, but never the email or the credential
+        host.set_caller(account(2));
+        let bytes = host.call(|| get_public_profile(account(1))).unwrap().unwrap();
+        assert!(!bytes.windows(b"alice@example.com".len()).any(|window| window == b"alice@example.com"));
+        let profile: PublicProfile = decode(Some(bytes));
+        assert_eq!((profile.handle.as_str(), profile.name.as_str()), ("alice", "Alice"));
+        assert_eq!((profile.location, profile.joined_at), (Some(String::from("Lisbon")), Some(1000)));
+        // Hidden fields are left out of the public profile but not the user's own
+        host.set_caller(account(1));
+        let display = DisplaySettings { show_location: false, show_join_date: false };
+        host.call(|| update_profile(ProfileUpdate { display: Some(display), ..no_changes() })).unwrap();
+        let profile: PublicProfile = decode(host.call(|| get_public_profile(account(1))).unwrap());
+        assert_eq!((profile.location, profile.joined_at), (None, None));
+        let own: PrivateProfile = decode(host.call(get_user).unwrap());
+        assert_eq!((own.email.as_str(), own.location.as_str(), own.joined_at), ("alice@example.com", "Lisbon", 1000));
+        assert_eq!(host.call(|| get_public_profile(account(3))), Err(CallError::Failed(TimeFeedError::UserNotFound)));
+    }
+
+    #[test]
+    fn profile_updates_change_only_the_given_fields() {
+        let host = new_host();
+        host.set_caller(account(1));
+        host.call(|| register_user(String::from("Alice"), String::from("alice"), String::from("alice@example.com"), b"correct horse".to_vec())).unwrap();
+        let links = vec![String::from("https://example.com/alice")];
+        let update = ProfileUpdate {
+            bio: Some(String::from("Writes about time")),
+            avatar: Some(Some(H256::repeat_byte(7))),
+            links: Some(links.clone()),
+            ..no_changes()
+        };
+        host.call(|| update_profile(update)).unwrap();
+        // Any bad field rejects the whole update
+        let rejected = [
+            (ProfileUpdate { name: Some(String::from("  ")), ..no_changes() }, TimeFeedError::InvalidName),
+            (ProfileUpdate { bio: Some("x".repeat(MAX_BIO_LEN + 1)), ..no_changes() }, TimeFeedError::InvalidBio),
+            (ProfileUpdate { location: Some("x".repeat(MAX_LOCATION_LEN + 1)), ..no_changes() }, TimeFeedError::InvalidLocation),
+            (ProfileUpdate { links: Some(vec![String::from("ftp://example.com")]), ..no_changes() }, TimeFeedError::InvalidLink),
+            (ProfileUpdate { links: Some(vec![links[0].clone(); MAX_LINKS + 1]), ..no_changes() }, TimeFeedError::InvalidLink),
+            (ProfileUpdate { name: Some(String::from("Eve")), bio: Some("x".repeat(MAX_BIO_LEN + 1)), ..no_changes() }, TimeFeedError::InvalidBio),
+        ];
+        for (update, error) in rejected {
+            assert_eq!(host.call(|| update_profile(update)), Err(CallError::Failed(error)));
+        }
+        // Fields left out keep their values, and the avatar can be removed
+        host.call(|| update_profile(ProfileUpdate { avatar: Some(None), ..no_changes() })).unwrap();
+        let own: PrivateProfile = decode(host.call(get_user).unwrap());
+        assert_eq!((own.name.as_str(), own.bio.as_str(), own.avatar), ("Alice", "Writes about time", None));
+        assert_eq!(own.links, links);
+        host.set_caller(account(2));
+        assert_eq!(host.call(|| update_profile(no_changes())), Err(CallError::Failed(TimeFeedError::UserNotFound)));
+    }
+
+    // Register a test account with a handle and email of its own
+    fn register(host: &InMemoryHost, n: u8) {
+        host.set_caller(account(n));
+        host.call(|| register_user(format!("User {}", n), format!("user{}", n), format!("user{}@example.com", n), b"correct horse".to_vec())).unwrap();
+    }
+
+    #[test]
+    fn follows_and_blocks_keep_both_sides_in_step() {
+        let host = new_host();
+        for n in 1..=3 {
+            register(&host, n);
+        }
+        host.set_caller(account(1));
+        host.call(|| follow(account(2))).unwrap();
+        assert_eq!(host.call(|| follow(account(2))), Err(CallError::Failed(TimeFeedError::AlreadyFollowing)));
+        assert_eq!(host.call(|| follow(account(1))), Err(CallError::Failed(TimeFeedError::CannotFollowSelf)));
+        host.set_caller(account(2));
+        host.call(|| follow(account(1))).unwrap();
+        // Blocking drops the follows both ways and stops either from following again
+        host.call(|| block_user(account(1))).unwrap();
+        let counts: FollowCounts = decode(host.call(|| get_follow_counts(account(1))).unwrap());
+        assert_eq!((counts.followers, counts.following), (0, 0));
+        host.set_caller(account(1));
+        assert_eq!(host.call(|| follow(account(2))), Err(CallError::Failed(TimeFeedError::Blocked)));
+        assert_eq!(host.call(|| unfollow(account(2))), Err(CallError::Failed(TimeFeedError::NotFollowing)));
+        // Only the account that blocked can unblock, and only once
+        assert_eq!(host.call(|| unblock_user(account(2))), Err(CallError::Failed(TimeFeedError::NotBlocked)));
+        host.set_caller(account(2));
+        host.call(|| unblock_user(account(1))).unwrap();
+        assert_eq!(host.call(|| unblock_user(account(1))), Err(CallError::Failed(TimeFeedError::NotBlocked)));
+        host.set_caller(account(1));
+        host.call(|| follow(account(2))).unwrap();
+    }
+
+    #[test]
+    fn follower_pages_continue_from_the_cursor() {
+        let host = new_host();
+        for n in 1..=4 {
+            register(&host, n);
+        }
+        for n in 2..=4 {
+            host.set_caller(account(n));
+            host.call(|| follow(account(1))).unwrap();
+        }
+        let first: AccountPage = decode(host.call(|| get_followers(account(1), 2, None)).unwrap());
+        assert_eq!(first.accounts, vec![account(2), account(3)]);
+        assert_eq!((first.total, first.next_cursor), (3, Some(account(3))));
+        let last: AccountPage = decode(host.call(|| get_followers(account(1), 2, first.next_cursor)).unwrap());
+        assert_eq!(last.accounts, vec![account(4)]);
+        assert_eq!(last.next_cursor, None);
+        assert_eq!(host.call(|| get_followers(account(1), 0, None)), Err(CallError::Failed(TimeFeedError::InvalidPageSize)));
+    }
+
+    #[test]
+    fn deleted_users_leave_the_follow_graph() {
+        let host = new_host();
+        for n in 1..=3 {
+            register(&host, n);
+        }
+        host.set_caller(account(1));
+        host.call(|| follow(account(2))).unwrap();
+        host.call(|| block_user(account(3))).unwrap();
+        host.set_caller(account(3));
+        host.call(|| follow(account(2))).unwrap();
+        host.set_caller(account(2));
+        host.call(|| follow(account(1))).unwrap();
+        host.set_caller(account(1));
+        host.call(delete_user).unwrap();
+        // Nobody follows or is followed by the deleted account, and its blocks are gone
+        let state: State = env::state();
+        for (follower, followees) in state.following.iter() {
+            assert!(*follower != account(1) && !followees.contains(&account(1)));
+            assert!(followees.iter().all(|followee| state.followers[followee].contains(follower)));
+        }
+        for (followee, followers) in state.followers.iter() {
+            assert!(*followee != account(1) && !followers.contains(&account(1)));
+            assert!(followers.iter().all(|follower| state.following[follower].contains(followee)));
+        }
+        assert!(!state.blocked.contains_key(&account(1)));
+        let counts: FollowCounts = decode(host.call(|| get_follow_counts(account(2))).unwrap());
+        assert_eq!((counts.followers, counts.following), (1, 0));
+    }
+}
@@ -0,0 +1,10 @@
+// Crate root for the TimeFeed contracts.
+// Module names follow the contract names, and struct fields are written out in
+// full throughout, so those two lints are switched off for the whole crate.
+#![allow(non_snake_case, clippy::redundant_field_names)]
+
+pub mod Error;
+pub mod Host;
+pub mod TokenManagement;
+pub mod UserManagement;
+pub mod PostManagement;